@@ -8,7 +8,7 @@
 //!
 //! ```
 //! # use bevy::prelude::*;
-//! # use bevy_simple_state_machine::SimpleStateMachinePlugin;
+//! # use bevy_simple_state_machine::prelude::*;
 //! App::new()
 //!     .add_plugins(DefaultPlugins)
 //!     .add_plugins(SimpleStateMachinePlugin::new());
@@ -17,34 +17,26 @@
 //! And then insert an `AnimationStateMachine` component on your entities
 //!
 //! ```
-//! # use bevy_simple_state_machine::*;
+//! # use bevy_simple_state_machine::prelude::*;
 //! # use bevy::{prelude::*, utils::HashMap};
 //! fn setup(mut commands: Commands) {
 //! # let idle_clip_handle: Handle<AnimationClip> = Handle::default();
 //! # let run_clip_handle: Handle<AnimationClip> = Handle::default();
 //!     let starting_state = "idle";
 //!     let my_states_map = HashMap::from([
-//!         ("idle", AnimationState{
-//!             name: "idle".to_string(),
-//!             clip: idle_clip_handle,
-//!             interruptible: true,
-//!         }),
-//!         ("run", AnimationState{
-//!             name: "run".to_string(),
-//!             clip: run_clip_handle,
-//!             interruptible: true,
-//!         }),
+//!         ("idle", AnimationState::new("idle", idle_clip_handle)),
+//!         ("run", AnimationState::new("run", run_clip_handle)),
 //!     ]);
 //!     let my_states_transitions_vec = vec![
 //!         StateMachineTransition::immediate(
 //!             AnimationStateRef::from_string("idle"),
 //!             AnimationStateRef::from_string("run"),
-//!             StateMachineTrigger::from(|vars| vars["run"].is_bool(true)),
+//!             StateMachineTrigger::from(|vars| vars.get("run").is_some_and(|run| run.is_bool(true))),
 //!         ),
 //!    ];
-//!     let state_machine_vars = HashMap::from([
-//!         ("run", StateMachineVariableType::Bool(false)),    
-//!     ]);
+//!     let state_machine_vars = [
+//!         ("run", StateMachineVariableType::Bool(false)),
+//!     ];
 //!      
 //!     commands.spawn(SpatialBundle::default())
 //!         .insert(AnimationPlayer::default())
@@ -61,13 +53,12 @@
 //! machine variables
 //!
 //! ```
-//! # use bevy_simple_state_machine::*;
-//! # use bevy::{prelude::*, utils::HashMap};
+//! # use bevy_simple_state_machine::prelude::*;
 //! # let mut state_machine = AnimationStateMachine::new(
 //! #   "idle",
-//! #   HashMap::default(),
+//! #   [],
 //! #   vec![],
-//! #   HashMap::from([("run", StateMachineVariableType::Bool(false))]),
+//! #   [("run", StateMachineVariableType::Bool(false))],
 //! # );
 //! state_machine.update_variable("run", StateMachineVariableType::Bool(true));
 //! ```
@@ -80,19 +71,60 @@
 //!  - Internal state machine variables
 //!
 //! Currently, transitions end on the same frame they are triggered.
+//!
+//! ## Cargo features
+//!
+//!  - `debug`: draws the state of every machine with gizmos, see [`SimpleStateMachinePlugin`]
+//!
+//! The most commonly used items can be imported at once from the [`prelude`]
 
 use std::{
+    cmp::Ordering,
+    collections::VecDeque,
     fmt::{Debug, Display},
+    ops::Range,
+    panic::{self, AssertUnwindSafe},
     sync::Arc,
     time::Duration,
 };
 
 use bevy::{
-    ecs::schedule::{InternedScheduleLabel, ScheduleLabel},
+    animation::RepeatAnimation,
+    ecs::{
+        schedule::{InternedScheduleLabel, ScheduleLabel},
+        system::SystemParam,
+    },
     prelude::*,
-    utils::HashMap,
+    utils::{HashMap, HashSet},
 };
 
+/// Commonly used types and functions
+///
+/// ```
+/// use bevy_simple_state_machine::prelude::*;
+///
+/// let variables: StateMachineVariables =
+///     state_machine_variables([("run", StateMachineVariableType::Bool(false))]);
+/// let state_machine = AnimationStateMachine::new(
+///     "idle".to_string(),
+///     [("idle".to_string(), AnimationState::empty("idle"))],
+///     vec![StateMachineTransition::from_any("idle", StateMachineTrigger::Never)],
+///     variables,
+/// );
+/// ```
+pub mod prelude {
+    pub use crate::{
+        state_machine_variables, AnimationMarkerEvent, AnimationState, AnimationStateMachine,
+        AnimationStateMachineBuilder, AnimationStateRef, ClipLibrary, Comparison, ComparisonOp,
+        EntitiesInState, FinishBehavior, ForceTransitionError, GlobalTransitionsOrder,
+        MultiMatchPolicy, PlaybackDirection, ResetAllStateMachines, SimpleStateMachinePlugin,
+        StateMachineBuildError, StateMachineInitializedEvent, StateMachineSet,
+        StateMachineSnapshot, StateMachineTick, StateMachineTransition, StateMachineTrigger,
+        StateMachineVariableType, StateMachineVariables, TransitionEndedEvent,
+        TransitionProgressEvent, TransitionRecord, TriggerContext, TriggerKind,
+    };
+}
+
 /// Plugin that handles all state machine executions
 ///
 /// Include this in your app to enable this crate
@@ -103,26 +135,114 @@ use bevy::{
 ///     .add_plugins(DefaultPlugins)
 ///     .add_plugins(SimpleStateMachinePlugin::new());
 /// ```
-
+///
+/// ## Debug overlay
+///
+/// With the `debug` feature, the plugin writes above each machine its current state, its
+/// variables and the transitions of the state, with their status: `fires`, `disabled` or `waiting`.
+/// States whose firing transitions are blocked, such as by a non interruptible clip, are marked
+/// `(blocked)`. A sphere colored after the state is drawn with gizmos below the text.
+/// The overlay is only drawn in apps with a UI camera and gizmos, such as apps using the
+/// `DefaultPlugins` with a spawned camera
 pub struct SimpleStateMachinePlugin {
     schedule: InternedScheduleLabel,
+    progress_events: bool,
+    finish_epsilon: f32,
+    auto_init: bool,
+    history_capacity: usize,
+    multi_match_policy: MultiMatchPolicy,
+    max_delta: Option<f32>,
+    stop_on_remove: bool,
+    state_bindings: Vec<StateBinding>,
 }
 
+/// Deferred registration of a [`SimpleStateMachinePlugin::bind_state`] system
+type StateBinding = Box<dyn Fn(&mut App, InternedScheduleLabel) + Send + Sync>;
+
 impl Plugin for SimpleStateMachinePlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<TransitionEndedEvent>()
-            .register_type::<AnimationStateMachine>()
-            .register_type::<AnimationStateRef>()
-            .register_type::<AnimationState>()
-            .register_type::<StateMachineVariableType>()
-            .register_type::<StateMachineTransition>()
-            .add_systems(
+        app.insert_resource(StateMachineSettings {
+            finish_epsilon: self.finish_epsilon,
+            history_capacity: self.history_capacity,
+            multi_match_policy: self.multi_match_policy,
+            max_delta: self.max_delta,
+        })
+        .add_event::<TransitionEndedEvent>()
+        .add_event::<TransitionProgressEvent>()
+        .add_event::<StateMachineInitializedEvent>()
+        .add_event::<ResetAllStateMachines>()
+        .add_event::<AnimationMarkerEvent>()
+        .register_type::<AnimationStateMachine>()
+        .register_type::<AnimationStateRef>()
+        .register_type::<AnimationState>()
+        .register_type::<PlaybackDirection>()
+        .register_type::<FinishBehavior>()
+        .register_type::<GlobalTransitionsOrder>()
+        .register_type::<MultiMatchPolicy>()
+        .register_type::<StateMachineVariableType>()
+        .register_type::<StateMachineTransition>()
+        .register_type::<StateMachineTick>()
+        .register_type::<TransitionRecord>()
+        .register_type::<TriggerKind>()
+        .register_type::<Comparison>()
+        .register_type::<ComparisonOp>()
+        .register_type::<StateMachineSnapshot>()
+        .add_systems(
+            self.schedule.to_owned(),
+            (
+                Self::check_transitions.in_set(StateMachineSet::StateMachineSet),
+                Self::reset_state_machines
+                    .before(Self::check_transitions)
+                    .before(Self::init_state_machines)
+                    .in_set(StateMachineSet::StateMachineSet),
+                Self::emit_marker_events
+                    .after(Self::check_transitions)
+                    .in_set(StateMachineSet::StateMachineSet),
+            ),
+        );
+        if self.auto_init {
+            app.add_systems(
                 self.schedule.to_owned(),
-                (
-                    Self::init_state_machines.in_set(StateMachineSet::StateMachineSet),
-                    Self::check_transitions.in_set(StateMachineSet::StateMachineSet),
-                ),
+                Self::init_state_machines
+                    .before(Self::check_transitions)
+                    .in_set(StateMachineSet::StateMachineSet),
             );
+        }
+        if self.progress_events {
+            app.add_systems(
+                self.schedule.to_owned(),
+                Self::emit_progress_events
+                    .after(Self::check_transitions)
+                    .in_set(StateMachineSet::StateMachineSet),
+            );
+        }
+        if self.stop_on_remove {
+            app.add_systems(
+                self.schedule.to_owned(),
+                Self::stop_removed_players
+                    .after(Self::check_transitions)
+                    .in_set(StateMachineSet::StateMachineSet),
+            );
+        }
+        #[cfg(feature = "debug")]
+        app.add_systems(
+            self.schedule.to_owned(),
+            Self::draw_debug_overlay
+                .after(Self::check_transitions)
+                .in_set(StateMachineSet::StateMachineSet)
+                .run_if(resource_exists::<bevy::gizmos::config::GizmoConfigStore>),
+        );
+        #[cfg(feature = "debug")]
+        app.add_systems(
+            self.schedule.to_owned(),
+            Self::update_debug_labels
+                .after(Self::check_transitions)
+                .in_set(StateMachineSet::StateMachineSet)
+                .run_if(resource_exists::<bevy::ui::UiScale>),
+        );
+        for bind in &self.state_bindings {
+            bind(app, self.schedule);
+        }
     }
 }
 
@@ -133,6 +253,9 @@ impl Default for SimpleStateMachinePlugin {
 }
 
 impl SimpleStateMachinePlugin {
+    /// Default value of [`SimpleStateMachinePlugin::finish_epsilon`], in seconds
+    pub const DEFAULT_FINISH_EPSILON: f32 = 0.001;
+
     /// Creates a new instance of [`SimpleStateMachinePlugin`]
     ///
     /// Its systems are scheduled in [`Update`]   
@@ -146,56 +269,735 @@ impl SimpleStateMachinePlugin {
     pub fn new_in_schedule(schedule: impl ScheduleLabel) -> Self {
         Self {
             schedule: schedule.intern(),
+            progress_events: false,
+            finish_epsilon: Self::DEFAULT_FINISH_EPSILON,
+            auto_init: true,
+            history_capacity: 0,
+            multi_match_policy: MultiMatchPolicy::default(),
+            max_delta: None,
+            stop_on_remove: false,
+            state_bindings: Vec::new(),
         }
     }
 
+    /// Enables or disables [`TransitionProgressEvent`]s
+    ///
+    /// They are disabled by default, since they are emitted on every frame of every blend
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_simple_state_machine::SimpleStateMachinePlugin;
+    /// App::new()
+    ///     .add_plugins(DefaultPlugins)
+    ///     .add_plugins(SimpleStateMachinePlugin::new().progress_events(true));
+    /// ```
+    pub fn progress_events(mut self, enabled: bool) -> Self {
+        self.progress_events = enabled;
+        self
+    }
+
+    /// Sets the tolerance, in seconds, used to consider a clip finished
+    ///
+    /// Non looping clips are considered finished once they are played
+    /// within this amount of time of their end, so that a transition does not
+    /// depend on the exact frame on which the clip ends
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_simple_state_machine::SimpleStateMachinePlugin;
+    /// App::new()
+    ///     .add_plugins(DefaultPlugins)
+    ///     .add_plugins(SimpleStateMachinePlugin::new().finish_epsilon(0.05));
+    /// ```
+    pub fn finish_epsilon(mut self, epsilon: f32) -> Self {
+        self.finish_epsilon = epsilon;
+        self
+    }
+
+    /// Enables or disables the automatic playback of the starting state
+    ///
+    /// When enabled, the starting state of newly added machines is always played
+    /// before their transitions are evaluated for the first time.
+    /// When disabled, newly added machines do not play anything nor evaluate their
+    /// transitions until [`AnimationStateMachine::play_current`] is called
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_simple_state_machine::prelude::*;
+    /// fn start_intro(
+    ///     mut query: Query<(&mut AnimationStateMachine, &mut AnimationPlayer), Added<AnimationStateMachine>>,
+    ///     animations: Res<Assets<AnimationClip>>,
+    /// ) {
+    ///     for (mut state_machine, mut player) in &mut query {
+    ///         state_machine.play_current(&mut player, &animations);
+    ///     }
+    /// }
+    ///
+    /// App::new()
+    ///     .add_plugins(DefaultPlugins)
+    ///     .add_plugins(SimpleStateMachinePlugin::new().auto_init(false))
+    ///     .add_systems(Update, start_intro);
+    /// ```
+    pub fn auto_init(mut self, enabled: bool) -> Self {
+        self.auto_init = enabled;
+        self
+    }
+
+    /// Keeps the last `capacity` transitions of every machine in its [`AnimationStateMachine::history`]
+    ///
+    /// The history is disabled by default, and a `capacity` of `0` disables it again
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_simple_state_machine::SimpleStateMachinePlugin;
+    /// App::new()
+    ///     .add_plugins(DefaultPlugins)
+    ///     .add_plugins(SimpleStateMachinePlugin::new().transition_history(16));
+    /// ```
+    pub fn transition_history(mut self, capacity: usize) -> Self {
+        self.history_capacity = capacity;
+        self
+    }
+
+    /// Sets how the transition is chosen when several transitions match on the same frame
+    ///
+    /// Only one transition is taken per frame, by default the one with the
+    /// highest [`StateMachineTransition::priority`]
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_simple_state_machine::{MultiMatchPolicy, SimpleStateMachinePlugin};
+    /// App::new()
+    ///     .add_plugins(DefaultPlugins)
+    ///     .add_plugins(SimpleStateMachinePlugin::new().multi_match_policy(MultiMatchPolicy::FirstMatch));
+    /// ```
+    pub fn multi_match_policy(mut self, policy: MultiMatchPolicy) -> Self {
+        self.multi_match_policy = policy;
+        self
+    }
+
+    /// Limits the time, in seconds, the machines advance by in a single frame
+    ///
+    /// This keeps time based triggers, such as [`StateMachineTrigger::AlwaysAfter`],
+    /// from skipping ahead after a frame spike. There is no limit by default
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_simple_state_machine::SimpleStateMachinePlugin;
+    /// App::new()
+    ///     .add_plugins(DefaultPlugins)
+    ///     .add_plugins(SimpleStateMachinePlugin::new().max_delta(0.1));
+    /// ```
+    pub fn max_delta(mut self, max_delta: f32) -> Self {
+        self.max_delta = Some(max_delta);
+        self
+    }
+
+    /// Pauses the [`AnimationPlayer`] driven by a machine once the machine is removed
+    ///
+    /// This is disabled by default, leaving the player on the last played clip
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_simple_state_machine::SimpleStateMachinePlugin;
+    /// App::new()
+    ///     .add_plugins(DefaultPlugins)
+    ///     .add_plugins(SimpleStateMachinePlugin::new().stop_on_remove(true));
+    /// ```
+    pub fn stop_on_remove(mut self, enabled: bool) -> Self {
+        self.stop_on_remove = enabled;
+        self
+    }
+
+    /// Binds the Bevy [`State`] `S` to the given variable of every [`AnimationStateMachine`]
+    ///
+    /// Whenever the state changes, the variable is set to a [`StateMachineVariableType::String`]
+    /// holding the [`Debug`] representation of the new state. Newly added machines
+    /// receive the current value too
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_simple_state_machine::SimpleStateMachinePlugin;
+    /// #[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+    /// enum GamePhase {
+    ///     #[default]
+    ///     Menu,
+    ///     Playing,
+    /// }
+    ///
+    /// App::new()
+    ///     .add_plugins(DefaultPlugins)
+    ///     .init_state::<GamePhase>()
+    ///     .add_plugins(SimpleStateMachinePlugin::new().bind_state::<GamePhase>("app_state"));
+    /// ```
+    pub fn bind_state<S: States>(mut self, variable: impl ToString) -> Self {
+        let variable = variable.to_string();
+        self.state_bindings.push(Box::new(move |app, schedule| {
+            app.add_systems(
+                schedule,
+                Self::sync_bound_state::<S>(variable.to_owned())
+                    .before(Self::check_transitions)
+                    .in_set(StateMachineSet::StateMachineSet),
+            );
+        }));
+        self
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn check_transitions(
-        mut state_machines_query: Query<(Entity, &mut AnimationStateMachine, &mut AnimationPlayer)>,
+        mut state_machines_query: Query<(Entity, &mut AnimationStateMachine)>,
+        mut players_query: Query<&mut AnimationPlayer>,
+        children_query: Query<&Children>,
         mut event_writer: EventWriter<TransitionEndedEvent>,
+        animations: Res<Assets<AnimationClip>>,
+        settings: Res<StateMachineSettings>,
+        time: Res<Time>,
+        tick: Option<Res<StateMachineTick>>,
+        mut unloaded_clips: Local<HashSet<AssetId<AnimationClip>>>,
     ) {
-        for (entity, mut state_machine, mut player) in &mut state_machines_query {
-            if let Some(current_state) = state_machine.current_state() {
-                if current_state.interruptible || player.is_finished() {
-                    for transition in state_machine.transitions_from_current_state() {
-                        if transition.trigger.evaluate(&state_machine.variables) {
-                            if let Some(next_state) =
-                                state_machine.get_state(transition.end_state.unwrap())
-                            {
-                                debug!("triggering {}", transition);
-                                state_machine.current_state = next_state.name;
-                                if let Some(transition_duration) = transition.transition_duration {
-                                    player
-                                        .play_with_transition(next_state.clip, transition_duration);
-                                } else {
-                                    player.play(next_state.clip);
-                                }
-                                event_writer.send(TransitionEndedEvent {
-                                    entity,
-                                    origin: current_state.state_ref(),
-                                    end: transition.end_state,
-                                });
-                            }
+        let delta = match settings.max_delta {
+            Some(max_delta) => time.delta_seconds().min(max_delta),
+            None => time.delta_seconds(),
+        };
+        for (entity, mut state_machine) in &mut state_machines_query {
+            state_machine.just_changed = false;
+            state_machine.multi_match_policy = settings.multi_match_policy;
+            let Some(player_entity) =
+                state_machine.resolve_player(entity, &players_query, &children_query)
+            else {
+                continue;
+            };
+            let Ok(mut player) = players_query.get_mut(player_entity) else {
+                continue;
+            };
+            if state_machine.stopped {
+                player.pause();
+                continue;
+            }
+            if !state_machine.initialized {
+                continue;
+            }
+            let delta = delta * state_machine.time_scale;
+            state_machine.tick(delta);
+            state_machine.current_tick = tick.as_deref().map(|tick| tick.0);
+            let Some(current_state) = state_machine.current_state() else {
+                continue;
+            };
+            if state_machine.replay {
+                state_machine.replay = false;
+                current_state.play(&mut player, &animations, None);
+            }
+            if let Some(transition_clip) = state_machine.transition_clips.front() {
+                let finished = match animations.get(transition_clip) {
+                    Some(clip) => {
+                        player.is_finished()
+                            || player.seek_time() >= clip.duration() - settings.finish_epsilon
+                    }
+                    None => {
+                        if unloaded_clips.insert(transition_clip.id()) {
+                            warn!(
+                                "transition clip into state \"{}\" is not loaded, waiting for it before playing the state",
+                                current_state.name
+                            );
                         }
+                        false
+                    }
+                };
+                if !finished {
+                    continue;
+                }
+                state_machine.transition_clips.pop_front();
+                if let Some(transition_clip) = state_machine.transition_clips.front() {
+                    play_transition_clip(&mut player, transition_clip, None);
+                    continue;
+                }
+                current_state.play(&mut player, &animations, None);
+                state_machine.play_additional(
+                    &current_state,
+                    &mut players_query,
+                    &animations,
+                    None,
+                );
+                player = match players_query.get_mut(player_entity) {
+                    Ok(player) => player,
+                    Err(_) => continue,
+                };
+            }
+            current_state.update_playback(&mut player, &animations);
+            // states hosting a sub machine play the clips of the child, which waits for them
+            state_machine.waiting_for_clip = current_state.sub_machine.is_none()
+                && !current_state.interruptible
+                && !animation_finished(
+                    &player,
+                    &current_state,
+                    &animations,
+                    settings.finish_epsilon,
+                );
+            if state_machine.waiting_for_clip {
+                let clip = current_state.active_clip();
+                if !current_state.is_empty()
+                    && !animations.contains(clip)
+                    && unloaded_clips.insert(clip.id())
+                {
+                    warn!(
+                        "clip of state \"{}\" is not loaded, waiting for it before leaving the state",
+                        current_state.name
+                    );
+                }
+            }
+            if !state_machine.lock_transitions {
+                if let Some((origin, transition)) =
+                    state_machine.update_sub_machine(delta, &mut player, &animations, &settings)
+                {
+                    // the variables may have been changed by the sub machine
+                    state_machine.invalidate_transitions();
+                    state_machine.just_changed = true;
+                    event_writer.send(TransitionEndedEvent {
+                        entity,
+                        origin,
+                        end: transition.end_state,
+                        trigger_kind: transition.trigger.kind(),
+                        tag: transition.tag,
+                    });
+                }
+            }
+            if state_machine.transitions_blocked() {
+                continue;
+            }
+            let Some(transition) = state_machine
+                .select_transition(
+                    &state_machine.variables,
+                    settings.multi_match_policy,
+                    state_machine.lazy_evaluation,
+                )
+                .cloned()
+            else {
+                state_machine.transitions_settled = true;
+                state_machine.dirty_transitions.clear();
+                continue;
+            };
+            let end_state = state_machine.resolve_end_state(&transition.end_state);
+            if !state_machine.states.contains_key(&end_state) {
+                continue;
+            }
+            debug!("triggering {}", transition);
+            let phase = state_machine.normalized_time(&player, &animations);
+            state_machine.enter_state(end_state);
+            let next_state = state_machine.current_state().unwrap().to_owned();
+            if let Some(on_take) = &transition.on_take {
+                (on_take)(&mut state_machine.variables);
+            }
+            state_machine.last_transition_tick = state_machine.current_tick;
+            state_machine.just_changed = true;
+            state_machine.blend = transition.transition_duration.map(|duration| ActiveBlend {
+                elapsed: 0.,
+                duration: duration.as_secs_f32(),
+            });
+            let transition_duration =
+                scaled_duration(transition.transition_duration, state_machine.time_scale);
+            state_machine.transition_clips = current_state
+                .outro_clip
+                .iter()
+                .chain(&transition.transition_clip)
+                .cloned()
+                .collect();
+            match state_machine.transition_clips.front() {
+                Some(clip) => play_transition_clip(&mut player, clip, transition_duration),
+                None => {
+                    next_state.play_from(
+                        &current_state,
+                        &mut player,
+                        &animations,
+                        transition_duration,
+                    );
+                    if let (Some(phase), Some(duration), true) = (
+                        phase,
+                        next_state.duration(&animations),
+                        current_state.sync_group.is_some()
+                            && current_state.sync_group == next_state.sync_group,
+                    ) {
+                        player.seek_to(phase * duration);
                     }
+                    // with transition clips, additional players start the state once they finished
+                    state_machine.play_additional(
+                        &next_state,
+                        &mut players_query,
+                        &animations,
+                        transition_duration,
+                    );
                 }
             }
+            state_machine.record_transition(
+                TransitionRecord {
+                    origin: current_state.state_ref(),
+                    end: next_state.state_ref(),
+                    timestamp: time.elapsed_seconds(),
+                },
+                settings.history_capacity,
+            );
+            event_writer.send(TransitionEndedEvent {
+                entity,
+                origin: current_state.state_ref(),
+                end: next_state.state_ref(),
+                trigger_kind: transition.trigger.kind(),
+                tag: transition.tag,
+            });
         }
     }
 
     fn init_state_machines(
-        mut state_machines_query: Query<
-            (&AnimationStateMachine, &mut AnimationPlayer),
-            Added<AnimationStateMachine>,
-        >,
+        mut state_machines_query: Query<(Entity, &mut AnimationStateMachine)>,
+        mut players_query: Query<&mut AnimationPlayer>,
+        children_query: Query<&Children>,
+        mut event_writer: EventWriter<StateMachineInitializedEvent>,
+        animations: Res<Assets<AnimationClip>>,
+        asset_server: Option<Res<AssetServer>>,
+        library: Option<Res<ClipLibrary>>,
     ) {
-        for (state_machine, mut player) in &mut state_machines_query {
+        for (entity, mut state_machine) in &mut state_machines_query {
+            if state_machine.initialized {
+                continue;
+            }
+            if let Some(asset_server) = &asset_server {
+                state_machine.load_clips(asset_server);
+            }
+            if let Some(library) = &library {
+                state_machine.resolve_library_clips(library);
+            }
+            let Some(mut player) = state_machine
+                .resolve_player(entity, &players_query, &children_query)
+                .and_then(|player_entity| players_query.get_mut(player_entity).ok())
+            else {
+                continue;
+            };
+            state_machine.initialized = true;
+            state_machine.choose_entry_state();
+            state_machine.select_clip();
+            state_machine.play_current(&mut player, &animations);
             if let Some(current_state) = state_machine.current_state() {
-                player.play(current_state.clip);
+                state_machine.play_additional(
+                    &current_state,
+                    &mut players_query,
+                    &animations,
+                    None,
+                );
+            }
+            if !state_machine.initialized_event_sent {
+                state_machine.initialized_event_sent = true;
+                event_writer.send(StateMachineInitializedEvent {
+                    entity,
+                    starting_state: AnimationStateRef::from_string(&state_machine.current_state),
+                });
+            }
+        }
+    }
+
+    fn emit_marker_events(
+        mut state_machines_query: Query<(Entity, &mut AnimationStateMachine)>,
+        players_query: Query<&AnimationPlayer>,
+        mut event_writer: EventWriter<AnimationMarkerEvent>,
+        animations: Res<Assets<AnimationClip>>,
+    ) {
+        for (entity, mut state_machine) in &mut state_machines_query {
+            let Some(player) = state_machine
+                .player_entity()
+                .and_then(|player_entity| players_query.get(player_entity).ok())
+            else {
+                continue;
+            };
+            let Some(state) = state_machine.states.get(&state_machine.current_state) else {
+                continue;
+            };
+            if state.markers.is_empty() || !state_machine.transition_clips.is_empty() {
+                continue;
+            }
+            let Some(time) = state_machine.normalized_time(player, &animations) else {
+                continue;
+            };
+            let completions = player.completions();
+            let looped = !player.is_finished() && completions != state_machine.marker_completions;
+            for (marker, label) in &state.markers {
+                let crossed = match state_machine.marker_time {
+                    None => *marker <= time,
+                    Some(previous) if looped => *marker > previous || *marker <= time,
+                    Some(previous) => previous < *marker && *marker <= time,
+                };
+                if crossed {
+                    event_writer.send(AnimationMarkerEvent {
+                        entity,
+                        state: state.state_ref(),
+                        label: label.to_owned(),
+                    });
+                }
+            }
+            state_machine.marker_time = Some(time);
+            state_machine.marker_completions = completions;
+        }
+    }
+
+    /// Draws a sphere above each machine, colored after its current state, surrounded by
+    /// a marker per transition of the current state, green if its trigger currently fires
+    #[cfg(feature = "debug")]
+    fn draw_debug_overlay(
+        state_machines_query: Query<(&AnimationStateMachine, &GlobalTransform)>,
+        mut gizmos: Gizmos,
+    ) {
+        use std::hash::{DefaultHasher, Hash, Hasher};
+
+        for (state_machine, transform) in &state_machines_query {
+            let mut hasher = DefaultHasher::new();
+            state_machine.current_state.hash(&mut hasher);
+            let hue = (hasher.finish() % 360) as f32;
+            let center = transform.translation() + Vec3::Y * 2.;
+            gizmos.sphere(center, Quat::IDENTITY, 0.2, Color::hsl(hue, 0.8, 0.5));
+            let transitions = state_machine.transitions_from(&state_machine.current_state);
+            let count = transitions.len() as f32;
+            for (i, transition) in transitions.into_iter().enumerate() {
+                let angle = std::f32::consts::TAU * i as f32 / count;
+                let color = match state_machine.can_take(transition, &state_machine.variables) {
+                    true => Color::GREEN,
+                    false => Color::RED,
+                };
+                let offset = Vec3::new(angle.cos(), 0., angle.sin()) * 0.4;
+                gizmos.sphere(center + offset, Quat::IDENTITY, 0.05, color);
+            }
+        }
+    }
+
+    /// Writes the current state, the variables and the transitions of each machine
+    /// in a text label, placed above the machine from the point of view of the first
+    /// active camera
+    #[cfg(feature = "debug")]
+    fn update_debug_labels(
+        mut commands: Commands,
+        state_machines_query: Query<(Entity, &AnimationStateMachine, &GlobalTransform)>,
+        cameras_query: Query<(&Camera, &GlobalTransform)>,
+        mut labels_query: Query<(&mut Text, &mut Style)>,
+        mut labels: Local<HashMap<Entity, Entity>>,
+    ) {
+        use std::fmt::Write;
+
+        labels.retain(|entity, label| {
+            let alive = state_machines_query.contains(*entity);
+            if !alive {
+                commands.entity(*label).despawn();
+            }
+            alive
+        });
+        let camera = cameras_query.iter().find(|(camera, _)| camera.is_active);
+        for (entity, state_machine, transform) in &state_machines_query {
+            let mut text = state_machine.current_state_name().to_owned();
+            let eligible = state_machine.eligible_transitions();
+            if !eligible.is_empty() && state_machine.next_transition().is_none() {
+                text.push_str(" (blocked)");
+            }
+            let mut variables: Vec<_> = state_machine.variables().iter().collect();
+            variables.sort_by_key(|(name, _)| *name);
+            for (name, value) in variables {
+                let _ = write!(text, "\n{name} = {value:?}");
+            }
+            for transition in state_machine.transitions_from(state_machine.current_state_name()) {
+                let status = if eligible
+                    .iter()
+                    .any(|other| std::ptr::eq(*other, transition))
+                {
+                    "fires"
+                } else if !transition.enabled {
+                    "disabled"
+                } else {
+                    "waiting"
+                };
+                let _ = write!(text, "\n-> {}: {status}", transition.end_state);
+            }
+            let position = camera.and_then(|(camera, camera_transform)| {
+                camera.world_to_viewport(camera_transform, transform.translation() + Vec3::Y * 2.5)
+            });
+            let Some((mut label_text, mut style)) = labels
+                .get(&entity)
+                .and_then(|label| labels_query.get_mut(*label).ok())
+            else {
+                let label = commands
+                    .spawn(
+                        TextBundle::from_section(
+                            text,
+                            TextStyle {
+                                font_size: 14.,
+                                ..default()
+                            },
+                        )
+                        .with_style(Style {
+                            display: bevy::ui::Display::None,
+                            position_type: PositionType::Absolute,
+                            ..default()
+                        }),
+                    )
+                    .id();
+                labels.insert(entity, label);
+                continue;
+            };
+            if label_text.sections[0].value != text {
+                label_text.sections[0].value = text;
+            }
+            match position {
+                Some(position) => {
+                    style.display = bevy::ui::Display::Flex;
+                    style.left = Val::Px(position.x);
+                    style.top = Val::Px(position.y);
+                }
+                None => style.display = bevy::ui::Display::None,
+            }
+        }
+    }
+
+    fn stop_removed_players(
+        mut removed: RemovedComponents<AnimationStateMachine>,
+        state_machines_query: Query<(Entity, &AnimationStateMachine)>,
+        mut players_query: Query<&mut AnimationPlayer>,
+        mut player_entities: Local<HashMap<Entity, Entity>>,
+    ) {
+        for entity in removed.read() {
+            let Some(player_entity) = player_entities.remove(&entity) else {
+                continue;
+            };
+            if let Ok(mut player) = players_query.get_mut(player_entity) {
+                player.pause();
+            }
+        }
+        player_entities.extend(
+            state_machines_query
+                .iter()
+                .filter_map(|(entity, state_machine)| {
+                    Some((entity, state_machine.player_entity()?))
+                }),
+        );
+    }
+
+    fn reset_state_machines(
+        mut events: EventReader<ResetAllStateMachines>,
+        mut state_machines_query: Query<&mut AnimationStateMachine>,
+    ) {
+        if events.read().count() == 0 {
+            return;
+        }
+        for mut state_machine in &mut state_machines_query {
+            state_machine.reset();
+        }
+    }
+
+    fn sync_bound_state<S: States>(
+        variable: String,
+    ) -> impl FnMut(Option<Res<State<S>>>, Query<&mut AnimationStateMachine>) {
+        move |state, mut state_machines_query| {
+            let Some(state) = state else {
+                return;
+            };
+            for mut state_machine in &mut state_machines_query {
+                if state.is_changed() || state_machine.is_added() {
+                    state_machine.update_variable(
+                        &variable,
+                        StateMachineVariableType::String(format!("{:?}", state.get())),
+                    );
+                }
+            }
+        }
+    }
+
+    fn emit_progress_events(
+        state_machines_query: Query<(Entity, &AnimationStateMachine)>,
+        mut event_writer: EventWriter<TransitionProgressEvent>,
+    ) {
+        for (entity, state_machine) in &state_machines_query {
+            if let Some(progress) = state_machine.transition_progress() {
+                event_writer.send(TransitionProgressEvent { entity, progress });
             }
         }
     }
 }
 
+/// Plugin configuration shared with the state machine systems
+#[derive(Resource)]
+struct StateMachineSettings {
+    finish_epsilon: f32,
+    history_capacity: usize,
+    multi_match_policy: MultiMatchPolicy,
+    max_delta: Option<f32>,
+}
+
+/// Tests if the clip of `state` played by `player` has finished
+///
+/// Non looping clips played forward are considered finished once they get
+/// within `epsilon` seconds of the end of the state [`AnimationState::duration`]
+fn animation_finished(
+    player: &AnimationPlayer,
+    state: &AnimationState,
+    animations: &Assets<AnimationClip>,
+    epsilon: f32,
+) -> bool {
+    if player.is_finished() {
+        return true;
+    }
+    let Some(duration) = state.duration(animations) else {
+        return false;
+    };
+    player.repeat_mode() == RepeatAnimation::Never
+        && !player.is_playback_reversed()
+        && player.seek_time() >= duration - epsilon
+}
+
+/// Compares two lists of transitions, ignoring the trigger functions
+fn transitions_structural_eq(a: &[StateMachineTransition], b: &[StateMachineTransition]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.structural_eq(b))
+}
+
+/// Plays a transition or outro clip once, from its start
+fn play_transition_clip(
+    player: &mut AnimationPlayer,
+    clip: &Handle<AnimationClip>,
+    transition_duration: Option<Duration>,
+) {
+    match transition_duration {
+        Some(duration) => player.start_with_transition(clip.to_owned(), duration),
+        None => player.start(clip.to_owned()),
+    };
+    player
+        .set_repeat(RepeatAnimation::Never)
+        .set_speed(1.)
+        .resume();
+}
+
+/// Converts a blend duration in machine time to the duration played by the [`AnimationPlayer`]
+///
+/// A frozen machine switches clips at once, since the crossfade of the player can't be paused
+fn scaled_duration(duration: Option<Duration>, time_scale: f32) -> Option<Duration> {
+    if time_scale == 0. {
+        return None;
+    }
+    duration.map(|duration| {
+        Duration::try_from_secs_f32(duration.as_secs_f32() / time_scale).unwrap_or(Duration::MAX)
+    })
+}
+
+/// Seek time from which a clip of the given duration is played in reverse
+///
+/// This is just before the end of the clip, since the [`AnimationPlayer`] wraps a seek time
+/// equal to the duration back to the start of the clip on frames without elapsed time
+fn reverse_start(duration: f32) -> f32 {
+    match duration > 0. {
+        true => f32::from_bits(duration.to_bits() - 1),
+        false => 0.,
+    }
+}
+
+/// Finds the closest entity with an [`AnimationPlayer`] among `root` and its descendants
+fn find_player(
+    root: Entity,
+    players_query: &Query<&mut AnimationPlayer>,
+    children_query: &Query<&Children>,
+) -> Option<Entity> {
+    let mut queue = VecDeque::from([root]);
+    while let Some(entity) = queue.pop_front() {
+        if players_query.contains(entity) {
+            return Some(entity);
+        }
+        if let Ok(children) = children_query.get(entity) {
+            queue.extend(children.iter());
+        }
+    }
+    None
+}
+
 /// State machine system label
 ///
 /// You can use this if you need a specific order for your systems
@@ -207,11 +1009,54 @@ pub enum StateMachineSet {
     StateMachineSet,
 }
 
+/// Policy used when several transitions from the current state match on the same frame
+///
+/// See [`SimpleStateMachinePlugin::multi_match_policy`]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum MultiMatchPolicy {
+    /// The first matching transition, in declaration order, is taken
+    FirstMatch,
+    /// The matching transition with the highest [`StateMachineTransition::priority`] is taken,
+    /// ties are resolved in declaration order
+    #[default]
+    HighestPriority,
+    /// Like [`MultiMatchPolicy::FirstMatch`], but a warning is logged when more than one transition matches
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::{MultiMatchPolicy, SimpleStateMachinePlugin};
+    /// let plugin = SimpleStateMachinePlugin::new().multi_match_policy(MultiMatchPolicy::WarnAndFirst);
+    /// ```
+    WarnAndFirst,
+}
+
 /// Internal state machine variables map type
 pub type StateMachineVariables = HashMap<String, StateMachineVariableType>;
 
+/// Builds a [`StateMachineVariables`] map from any collection of named variables
+///
+/// Example
+/// ```
+/// # use bevy_simple_state_machine::*;
+/// let variables = state_machine_variables([
+///     ("run", StateMachineVariableType::Bool(false)),
+///     ("speed", StateMachineVariableType::F32(0.)),
+/// ]);
+/// assert!(variables.get("run").is_some_and(|run| run.is_bool(false)));
+/// assert!(variables.get("speed").is_some_and(|speed| speed.is_f32(0.)));
+/// assert_eq!(variables.len(), 2);
+/// ```
+pub fn state_machine_variables<T: ToString>(
+    variables: impl IntoIterator<Item = (T, StateMachineVariableType)>,
+) -> StateMachineVariables {
+    variables
+        .into_iter()
+        .map(|(name, var)| (name.to_string(), var))
+        .collect()
+}
+
 /// State machine variable type
-#[derive(Clone, Reflect, PartialEq)]
+#[derive(Debug, Clone, Reflect, PartialEq)]
 pub enum StateMachineVariableType {
     /// Stores a bool
     Bool(bool),
@@ -223,6 +1068,14 @@ pub enum StateMachineVariableType {
     U32(u32),
     /// Stores a String
     String(String),
+    /// Stores a Color
+    Color(Color),
+    /// Stores a one-shot trigger
+    ///
+    /// See [`AnimationStateMachine::set_trigger`] and [`StateMachineTransition::on_trigger`]
+    Trigger(bool),
+    /// Stores a set of Strings, such as tags
+    StringSet(HashSet<String>),
 }
 
 impl StateMachineVariableType {
@@ -245,6 +1098,137 @@ impl StateMachineVariableType {
     pub fn is_f32(&self, value: f32) -> bool {
         *self == Self::F32(value)
     }
+
+    /// Tests if the variable is equal to the given value
+    ///
+    /// The comparison is exact, no tolerance is applied to the color channels
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::StateMachineVariableType;
+    /// # use bevy::prelude::Color;
+    /// let variable = StateMachineVariableType::Color(Color::RED);
+    /// assert!(variable.is_color(Color::RED));
+    /// assert!(!variable.is_color(Color::rgb(0.9, 0.0, 0.0)));
+    /// ```
+    pub fn is_color(&self, value: Color) -> bool {
+        *self == Self::Color(value)
+    }
+
+    /// Tests if the variable is a [`StateMachineVariableType::StringSet`] containing the given value
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::StateMachineVariableType;
+    /// # use bevy::utils::HashSet;
+    /// let tags = StateMachineVariableType::StringSet(HashSet::from(["airborne".to_string()]));
+    /// assert!(tags.contains("airborne"));
+    /// assert!(!tags.contains("grounded"));
+    /// ```
+    pub fn contains(&self, value: &str) -> bool {
+        match self {
+            Self::StringSet(set) => set.contains(value),
+            _ => false,
+        }
+    }
+
+    /// Gets the name of the variant holding the value
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Bool(_) => "Bool",
+            Self::F32(_) => "F32",
+            Self::I32(_) => "I32",
+            Self::U32(_) => "U32",
+            Self::String(_) => "String",
+            Self::Color(_) => "Color",
+            Self::Trigger(_) => "Trigger",
+            Self::StringSet(_) => "StringSet",
+        }
+    }
+
+    /// Converts numeric variables to an `f32`
+    ///
+    /// Returns `None` for non numeric variables
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::StateMachineVariableType;
+    /// assert_eq!(StateMachineVariableType::I32(-3).as_f32(), Some(-3.));
+    /// assert_eq!(StateMachineVariableType::Bool(true).as_f32(), None);
+    /// ```
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            Self::F32(value) => Some(*value),
+            Self::I32(value) => Some(*value as f32),
+            Self::U32(value) => Some(*value as f32),
+            _ => None,
+        }
+    }
+}
+
+/// Conversions from the wrapped types
+///
+/// Integer literals need a suffix, as they could be both an `i32` and an `u32`
+///
+/// Example
+/// ```
+/// # use bevy_simple_state_machine::*;
+/// # use bevy::prelude::Color;
+/// let variables = state_machine_variables([
+///     ("run", true.into()),
+///     ("speed", 1.5.into()),
+///     ("lives", 3i32.into()),
+///     ("combo", 2u32.into()),
+///     ("weapon", "sword".into()),
+///     ("tint", Color::RED.into()),
+/// ]);
+/// assert_eq!(variables["run"], StateMachineVariableType::Bool(true));
+/// assert_eq!(variables["speed"], StateMachineVariableType::F32(1.5));
+/// assert_eq!(variables["lives"], StateMachineVariableType::I32(3));
+/// assert_eq!(variables["combo"], StateMachineVariableType::U32(2));
+/// assert_eq!(variables["weapon"], StateMachineVariableType::String("sword".to_string()));
+/// assert_eq!(variables["tint"], StateMachineVariableType::Color(Color::RED));
+/// ```
+impl From<bool> for StateMachineVariableType {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<f32> for StateMachineVariableType {
+    fn from(value: f32) -> Self {
+        Self::F32(value)
+    }
+}
+
+impl From<i32> for StateMachineVariableType {
+    fn from(value: i32) -> Self {
+        Self::I32(value)
+    }
+}
+
+impl From<u32> for StateMachineVariableType {
+    fn from(value: u32) -> Self {
+        Self::U32(value)
+    }
+}
+
+impl From<String> for StateMachineVariableType {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<&str> for StateMachineVariableType {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<Color> for StateMachineVariableType {
+    fn from(value: Color) -> Self {
+        Self::Color(value)
+    }
 }
 
 /// Main state machine component
@@ -252,40 +1236,33 @@ impl StateMachineVariableType {
 /// Insert this on the entity you want to control with the state machine.
 ///
 /// ## Note:
-/// To function, the component requires an [`AnimationPlayer`] on the same entity.
+/// To function, the component requires an [`AnimationPlayer`] on the same entity,
+/// on one of its descendants, or on the entity set with [`AnimationStateMachine::with_player_entity`].
 ///
 /// ---
 ///
 /// Example
 /// ```
-/// # use bevy_simple_state_machine::*;
+/// # use bevy_simple_state_machine::prelude::*;
 /// # use bevy::{prelude::*, utils::HashMap};
 /// fn setup(mut commands: Commands) {
 /// # let idle_clip_handle: Handle<AnimationClip> = Handle::default();
 /// # let run_clip_handle: Handle<AnimationClip> = Handle::default();
 ///     let starting_state = "idle";
 ///     let my_states_map = HashMap::from([
-///         ("idle", AnimationState{
-///             name: "idle".to_string(),
-///             clip: idle_clip_handle,
-///             interruptible: true,
-///         }),
-///         ("run", AnimationState{
-///             name: "run".to_string(),
-///             clip: run_clip_handle,
-///             interruptible: true,
-///         }),
+///         ("idle", AnimationState::new("idle", idle_clip_handle)),
+///         ("run", AnimationState::new("run", run_clip_handle)),
 ///     ]);
 ///     let my_states_transitions_vec = vec![
 ///         StateMachineTransition::immediate(
 ///             AnimationStateRef::from_string("idle"),
 ///             AnimationStateRef::from_string("run"),
-///             StateMachineTrigger::from(|vars| vars["run"].is_bool(true)),
+///             StateMachineTrigger::from(|vars| vars.get("run").is_some_and(|run| run.is_bool(true))),
 ///         ),
 ///     ];
-///     let state_machine_vars = HashMap::from([
+///     let state_machine_vars = [
 ///         ("run", StateMachineVariableType::Bool(false)),
-///     ]);
+///     ];
 ///      
 ///     commands.spawn(SpatialBundle::default())
 ///         .insert(AnimationPlayer::default())
@@ -297,85 +1274,2980 @@ impl StateMachineVariableType {
 ///         ));
 /// }
 /// ```
-#[derive(Component, Default, Reflect)]
+#[derive(Component, Clone, Reflect)]
 #[reflect(Component)]
 pub struct AnimationStateMachine {
     current_state: String,
     states: HashMap<String, AnimationState>,
     transitions: Vec<StateMachineTransition>,
     variables: StateMachineVariables,
+    initial_variables: StateMachineVariables,
+    time_in_state: f32,
+    blend: Option<ActiveBlend>,
+    previous_values: HashMap<String, f32>,
+    deltas: HashMap<String, f32>,
+    suppress_redundant_transitions: bool,
+    entry_counts: HashMap<String, u32>,
+    current_tick: Option<u64>,
+    last_transition_tick: Option<u64>,
+    player_entity: Option<Entity>,
+    initialized: bool,
+    initialized_event_sent: bool,
+    starting_state: String,
+    stopped: bool,
+    history: Vec<TransitionRecord>,
+    entry_transitions: Vec<StateMachineTransition>,
+    previous_state: Option<String>,
+    constraints: HashMap<String, VariableConstraint>,
+    global_transitions: Vec<StateMachineTransition>,
+    global_transitions_order: GlobalTransitionsOrder,
+    marker_time: Option<f32>,
+    marker_completions: u32,
+    interruptible_blends: bool,
+    lock_transitions: bool,
+    multi_match_policy: MultiMatchPolicy,
+    waiting_for_clip: bool,
+    time_scale: f32,
+    #[reflect(ignore)]
+    resolver: Option<TransitionResolver>,
+    transition_clips: VecDeque<Handle<AnimationClip>>,
+    lazy_evaluation: bool,
+    transition_inputs: HashMap<String, Vec<usize>>,
+    transitions_settled: bool,
+    dirty_transitions: HashSet<usize>,
+    just_changed: bool,
+    blend_completed: bool,
+    replay: bool,
+    additional_players: Vec<AdditionalPlayer>,
+}
+
+/// Extra [`AnimationPlayer`] driven by a machine, see [`AnimationStateMachine::with_additional_player`]
+#[derive(Debug, Clone, Reflect)]
+struct AdditionalPlayer {
+    entity: Entity,
+    clip_overrides: HashMap<String, Handle<AnimationClip>>,
+}
+
+/// Function redirecting the end state of the transitions taken by a machine,
+/// see [`AnimationStateMachine::set_transition_resolver`]
+pub type TransitionResolver =
+    Arc<dyn Fn(&AnimationStateRef, &StateMachineVariables) -> AnimationStateRef + Send + Sync>;
+
+impl Default for AnimationStateMachine {
+    fn default() -> Self {
+        Self {
+            current_state: String::default(),
+            states: HashMap::default(),
+            transitions: Vec::default(),
+            variables: StateMachineVariables::default(),
+            initial_variables: StateMachineVariables::default(),
+            time_in_state: 0.,
+            blend: None,
+            previous_values: HashMap::default(),
+            deltas: HashMap::default(),
+            suppress_redundant_transitions: true,
+            entry_counts: HashMap::default(),
+            current_tick: None,
+            last_transition_tick: None,
+            player_entity: None,
+            initialized: false,
+            initialized_event_sent: false,
+            starting_state: String::default(),
+            stopped: false,
+            history: Vec::default(),
+            entry_transitions: Vec::default(),
+            previous_state: None,
+            constraints: HashMap::default(),
+            global_transitions: Vec::default(),
+            global_transitions_order: GlobalTransitionsOrder::default(),
+            marker_time: None,
+            marker_completions: 0,
+            interruptible_blends: true,
+            lock_transitions: false,
+            multi_match_policy: MultiMatchPolicy::default(),
+            waiting_for_clip: false,
+            time_scale: 1.,
+            resolver: None,
+            transition_clips: VecDeque::new(),
+            lazy_evaluation: false,
+            transition_inputs: HashMap::default(),
+            transitions_settled: false,
+            dirty_transitions: HashSet::default(),
+            just_changed: false,
+            blend_completed: false,
+            replay: false,
+            additional_players: Vec::new(),
+        }
+    }
+}
+
+impl Debug for AnimationStateMachine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AnimationStateMachine")
+            .field("current_state", &self.current_state)
+            .field("states", &self.states)
+            .field(
+                "transitions",
+                &self
+                    .transitions
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>(),
+            )
+            .field("variables", &self.variables)
+            .finish_non_exhaustive()
+    }
 }
 
 impl AnimationStateMachine {
     /// Creates a new [`AnimationStateMachine`]
+    ///
+    /// States and variables can be passed as any collection of named values,
+    /// such as a [`HashMap`] or an array of tuples
     pub fn new<T: ToString>(
         current_state: T,
-        states: HashMap<T, AnimationState>,
+        states: impl IntoIterator<Item = (T, AnimationState)>,
         transitions: Vec<StateMachineTransition>,
-        variables: HashMap<T, StateMachineVariableType>,
+        variables: impl IntoIterator<Item = (T, StateMachineVariableType)>,
     ) -> Self {
-        Self {
+        let variables = state_machine_variables(variables);
+        let mut state_machine = Self {
             current_state: current_state.to_string(),
+            starting_state: current_state.to_string(),
             states: states
-                .iter()
-                .map(|(name, state)| (name.to_string(), state.to_owned()))
+                .into_iter()
+                .map(|(name, state)| (name.to_string(), state))
                 .collect(),
             transitions,
-            variables: variables
-                .iter()
-                .map(|(name, var)| (name.to_string(), var.to_owned()))
-                .collect(),
-        }
+            initial_variables: variables.to_owned(),
+            variables,
+            ..Default::default()
+        };
+        state_machine.index_transitions();
+        state_machine
     }
 
-    #[inline]
-    fn current_state(&self) -> Option<AnimationState> {
+    /// Creates a new [`AnimationStateMachine`], naming each state after its [`AnimationState::name`]
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let state_machine = AnimationStateMachine::from_states(
+    ///     "idle",
+    ///     [AnimationState::empty("idle"), AnimationState::empty("run")],
+    ///     vec![StateMachineTransition::always("idle", "run")],
+    ///     [("speed", StateMachineVariableType::F32(0.))],
+    /// );
+    /// assert_eq!(state_machine.state("run").unwrap().name, "run");
+    /// assert_eq!(state_machine.evaluate_transition(), Some("run".into()));
+    /// ```
+    pub fn from_states<T: ToString>(
+        current_state: T,
+        states: impl IntoIterator<Item = AnimationState>,
+        transitions: Vec<StateMachineTransition>,
+        variables: impl IntoIterator<Item = (T, StateMachineVariableType)>,
+    ) -> Self {
+        let variables = state_machine_variables(variables);
+        let mut state_machine = Self {
+            current_state: current_state.to_string(),
+            starting_state: current_state.to_string(),
+            states: states
+                .into_iter()
+                .map(|state| (state.name.to_owned(), state))
+                .collect(),
+            transitions,
+            initial_variables: variables.to_owned(),
+            variables,
+            ..Default::default()
+        };
+        state_machine.index_transitions();
+        state_machine
+    }
+
+    /// Creates an [`AnimationStateMachineBuilder`] starting in the given state
+    pub fn builder<T: ToString>(starting_state: T) -> AnimationStateMachineBuilder {
+        AnimationStateMachineBuilder {
+            state_machine: Self::new(starting_state.to_string(), [], vec![], []),
+            duplicate_state: None,
+        }
+    }
+
+    /// Adds the states, transitions, entry transitions and variables of `other` to the machine
+    ///
+    /// Variables already present in the machine keep their value, the others are also kept by
+    /// [`AnimationStateMachine::reset_with_variables`]. If any state of `other`
+    /// has the same name as a state of the machine, nothing is added and an error is returned
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let mut locomotion = AnimationStateMachine::builder("idle")
+    ///     .add_state(AnimationState::empty("idle"))
+    ///     .build()
+    ///     .unwrap();
+    /// let combat = AnimationStateMachine::builder("attack")
+    ///     .add_state(AnimationState::empty("attack"))
+    ///     .add_transition(StateMachineTransition::always("attack", "idle"))
+    ///     .build()
+    ///     .unwrap();
+    /// assert!(locomotion.merge(combat.clone()).is_ok());
+    ///
+    /// let combat = AnimationStateMachine::builder("block")
+    ///     .add_state(AnimationState::empty("block"))
+    ///     .add_variable("stamina", 10.into())
+    ///     .build()
+    ///     .unwrap();
+    /// locomotion.merge(combat).unwrap();
+    /// locomotion.update_variable("stamina", 0.into());
+    /// locomotion.reset_with_variables();
+    /// assert_eq!(locomotion.variable("stamina"), Some(&StateMachineVariableType::I32(10)));
+    ///
+    /// let combat = AnimationStateMachine::builder("attack")
+    ///     .add_state(AnimationState::empty("attack"))
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     locomotion.merge(combat),
+    ///     Err(StateMachineBuildError::DuplicateState("attack".to_string())),
+    /// );
+    /// ```
+    pub fn merge(&mut self, other: AnimationStateMachine) -> Result<(), StateMachineBuildError> {
+        if let Some(name) = other
+            .states
+            .keys()
+            .find(|name| self.states.contains_key(*name))
+        {
+            return Err(StateMachineBuildError::DuplicateState(name.to_owned()));
+        }
+        self.states.extend(other.states);
+        self.transitions.extend(other.transitions);
+        self.global_transitions.extend(other.global_transitions);
+        self.entry_transitions.extend(other.entry_transitions);
+        for (name, value) in other.variables {
+            self.variables.entry(name).or_insert(value);
+        }
+        for (name, value) in other.initial_variables {
+            self.initial_variables.entry(name).or_insert(value);
+        }
+        self.index_transitions();
+        Ok(())
+    }
+
+    /// Sets the transitions used to choose the actual starting state
+    ///
+    /// When the machine is initialized, the first entry transition whose trigger fires
+    /// selects the state to start in, their `start_state` is ignored.
+    /// If none fires, the machine starts in the state passed to [`AnimationStateMachine::new`]
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let state_machine = AnimationStateMachine::from_states(
+    ///     "idle",
+    ///     ["idle", "aim"].map(AnimationState::empty),
+    ///     vec![],
+    ///     [("armed", true.into())],
+    /// )
+    /// .with_entry_transitions(vec![StateMachineTransition::from_any(
+    ///     "aim",
+    ///     StateMachineTrigger::from(|vars| vars.get("armed").is_some_and(|armed| armed.is_bool(true))),
+    /// )]);
+    /// ```
+    pub fn with_entry_transitions(
+        mut self,
+        entry_transitions: Vec<StateMachineTransition>,
+    ) -> Self {
+        self.entry_transitions = entry_transitions;
+        self
+    }
+
+    /// Enables or disables every transition between the given states, without removing them
+    ///
+    /// Returns `false` if there is no such transition
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let mut state_machine = AnimationStateMachine::new(
+    ///     "idle",
+    ///     [
+    ///         ("idle", AnimationState::empty("idle")),
+    ///         ("dash", AnimationState::empty("dash")),
+    ///     ],
+    ///     vec![StateMachineTransition::always("idle", "dash")],
+    ///     [],
+    /// );
+    /// assert!(state_machine.set_transition_enabled("idle", "dash", false));
+    /// assert_eq!(state_machine.evaluate_transition(), None);
+    /// state_machine.set_transition_enabled("idle", "dash", true);
+    /// assert_eq!(state_machine.evaluate_transition(), Some("dash".into()));
+    /// ```
+    pub fn set_transition_enabled(
+        &mut self,
+        from: impl Into<AnimationStateRef>,
+        to: impl Into<AnimationStateRef>,
+        enabled: bool,
+    ) -> bool {
+        let (from, to) = (from.into(), to.into());
+        let mut found = false;
+        for transition in self
+            .transitions
+            .iter_mut()
+            .chain(&mut self.global_transitions)
+            .filter(|transition| transition.start_state == from && transition.end_state == to)
+        {
+            transition.enabled = enabled;
+            found = true;
+        }
+        self.invalidate_transitions();
+        found
+    }
+
+    /// Adds a transition to the machine
+    pub fn add_transition(&mut self, transition: StateMachineTransition) {
+        self.transitions.push(transition);
+        self.index_transitions();
+    }
+
+    /// Adds a transition from [`AnimationStateRef::AnyState`] to the given state
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let mut state_machine = AnimationStateMachine::from_states(
+    ///     "idle",
+    ///     ["idle", "run", "stun"].map(AnimationState::empty),
+    ///     vec![],
+    ///     [("stunned", true.into())],
+    /// );
+    /// state_machine.add_global_always(
+    ///     "stun",
+    ///     StateMachineTrigger::from(|vars| vars.get("stunned").is_some_and(|stunned| stunned.is_bool(true))),
+    /// );
+    /// assert_eq!(state_machine.evaluate_transition(), Some("stun".into()));
+    /// state_machine.apply_transition(&"run".into());
+    /// assert_eq!(state_machine.evaluate_transition(), Some("stun".into()));
+    /// ```
+    pub fn add_global_always<T: ToString>(&mut self, to: T, trigger: StateMachineTrigger) {
+        self.add_transition(StateMachineTransition::from_any(to, trigger));
+    }
+
+    /// Sets the transitions evaluated from every state, such as "go to `dead` when out of health"
+    ///
+    /// Their `start_state` is replaced by [`AnimationStateRef::AnyState`]. They are kept apart
+    /// from the per state transitions, and evaluated after them unless changed with
+    /// [`AnimationStateMachine::set_global_transitions_order`]
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let mut state_machine = AnimationStateMachine::new(
+    ///     "idle",
+    ///     [
+    ///         ("idle", AnimationState::empty("idle")),
+    ///         ("run", AnimationState::empty("run")),
+    ///         ("dead", AnimationState::empty("dead")),
+    ///     ],
+    ///     vec![StateMachineTransition::always("idle", "run")],
+    ///     [("health", StateMachineVariableType::F32(0.))],
+    /// )
+    /// .with_global_transitions(vec![StateMachineTransition::from_any(
+    ///     "dead",
+    ///     StateMachineTrigger::from(|vars| vars.get("health").is_some_and(|health| health.is_f32(0.))),
+    /// )]);
+    /// assert_eq!(state_machine.evaluate_transition(), Some("run".into()));
+    /// state_machine.set_global_transitions_order(GlobalTransitionsOrder::BeforeLocal);
+    /// assert_eq!(state_machine.evaluate_transition(), Some("dead".into()));
+    /// state_machine.apply_transition(&"run".into());
+    /// assert_eq!(state_machine.evaluate_transition(), Some("dead".into()));
+    /// ```
+    pub fn with_global_transitions(
+        mut self,
+        global_transitions: Vec<StateMachineTransition>,
+    ) -> Self {
+        self.global_transitions = global_transitions
+            .into_iter()
+            .map(|transition| StateMachineTransition {
+                start_state: AnimationStateRef::AnyState,
+                ..transition
+            })
+            .collect();
+        self.index_transitions();
+        self
+    }
+
+    /// Sets whether the global transitions are evaluated before or after the per state ones
+    pub fn set_global_transitions_order(&mut self, order: GlobalTransitionsOrder) {
+        self.global_transitions_order = order;
+        self.invalidate_transitions();
+    }
+
+    /// Halts the machine and pauses its [`AnimationPlayer`]
+    ///
+    /// A stopped machine keeps its current state but does not evaluate any transition until
+    /// it is reactivated with [`AnimationStateMachine::reset`], [`AnimationStateMachine::restore`],
+    /// [`AnimationStateMachine::apply_transition`] or [`AnimationStateMachine::force_transition`]
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let mut state_machine = AnimationStateMachine::from_states(
+    ///     "idle",
+    ///     ["idle", "run"].map(AnimationState::empty),
+    ///     vec![StateMachineTransition::always("idle", "run")],
+    ///     [],
+    /// );
+    /// state_machine.stop();
+    /// assert!(state_machine.is_stopped());
+    /// assert_eq!(state_machine.evaluate_transition(), None);
+    /// state_machine.reset();
+    /// assert_eq!(state_machine.evaluate_transition(), Some("run".into()));
+    /// ```
+    pub fn stop(&mut self) {
+        self.stopped = true;
+        self.blend = None;
+    }
+
+    /// Tests if the machine has been halted with [`AnimationStateMachine::stop`]
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// Returns the machine to its starting state and replays it
+    ///
+    /// Variables keep their current values, see [`AnimationStateMachine::reset_with_variables`].
+    /// This also reactivates a stopped machine. The starting state is
+    /// played again by the initialization system, unless disabled with
+    /// [`SimpleStateMachinePlugin::auto_init`], in which case the machine waits
+    /// for [`AnimationStateMachine::play_current`]
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let mut state_machine = AnimationStateMachine::new(
+    ///     "idle",
+    ///     [("idle", AnimationState::empty("idle"))],
+    ///     vec![],
+    ///     [],
+    /// );
+    /// state_machine.stop();
+    /// assert!(state_machine.is_stopped());
+    /// state_machine.reset();
+    /// assert!(!state_machine.is_stopped());
+    /// ```
+    pub fn reset(&mut self) {
+        self.current_state = self.starting_state.to_owned();
+        self.previous_state = None;
+        self.time_in_state = 0.;
+        self.marker_time = None;
+        self.blend = None;
+        self.transition_clips.clear();
+        self.invalidate_transitions();
+        self.stopped = false;
+        self.initialized = false;
+    }
+
+    /// Like [`AnimationStateMachine::reset`], also restoring the variables the machine was created with
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let mut state_machine = AnimationStateMachine::builder("idle")
+    ///     .add_state(AnimationState::empty("idle"))
+    ///     .add_variable("health", 100.into())
+    ///     .build()
+    ///     .unwrap();
+    /// state_machine.update_variable("health", 0.into());
+    /// state_machine.update_variable("dead", true.into());
+    /// state_machine.reset_with_variables();
+    /// assert_eq!(state_machine.variable("health"), Some(&StateMachineVariableType::I32(100)));
+    /// assert_eq!(state_machine.variable("dead"), None);
+    /// ```
+    pub fn reset_with_variables(&mut self) {
+        self.reset();
+        self.variables = self.initial_variables.to_owned();
+    }
+
+    /// Captures the current state, variables and timing of the machine
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let mut state_machine = AnimationStateMachine::from_states(
+    ///     "idle",
+    ///     ["idle", "run"].map(AnimationState::empty),
+    ///     vec![],
+    ///     [("speed", 1.0.into())],
+    /// );
+    /// let snapshot = state_machine.snapshot();
+    /// state_machine.apply_transition(&"run".into());
+    /// state_machine.update_variable("speed", 5.0.into());
+    /// state_machine.restore(snapshot.clone());
+    /// assert!(state_machine.is_current_state("idle"));
+    /// assert_eq!(state_machine.variable("speed"), Some(&StateMachineVariableType::F32(1.)));
+    /// assert_eq!(state_machine.snapshot(), snapshot);
+    /// ```
+    pub fn snapshot(&self) -> StateMachineSnapshot {
+        StateMachineSnapshot {
+            current_state: self.current_state.to_owned(),
+            variables: self.variables.to_owned(),
+            time_in_state: self.time_in_state,
+            previous_state: self.previous_state.to_owned(),
+        }
+    }
+
+    /// Brings the machine back to a state captured with [`AnimationStateMachine::snapshot`]
+    ///
+    /// Active blends and transition clips are dropped, a stopped machine is reactivated,
+    /// and the plugin plays the clip of the restored state on the next frame
+    pub fn restore(&mut self, snapshot: StateMachineSnapshot) {
+        self.current_state = snapshot.current_state;
+        self.variables = snapshot.variables;
+        self.time_in_state = snapshot.time_in_state;
+        self.previous_state = snapshot.previous_state;
+        self.blend = None;
+        self.transition_clips.clear();
+        self.marker_time = None;
+        self.invalidate_transitions();
+        self.stopped = false;
+        self.replay = true;
+        self.select_clip();
+    }
+
+    /// Last transitions taken by the machine, from the oldest to the newest
+    ///
+    /// This is empty unless enabled with [`SimpleStateMachinePlugin::transition_history`]
+    ///
+    /// Example
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_simple_state_machine::AnimationStateMachine;
+    /// fn log_history(query: Query<&AnimationStateMachine>) {
+    ///     for state_machine in &query {
+    ///         for record in state_machine.history() {
+    ///             info!("{} -> {} at {}s", record.origin, record.end, record.timestamp);
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn history(&self) -> &[TransitionRecord] {
+        &self.history
+    }
+
+    /// Sets the entity holding the [`AnimationPlayer`] driven by the machine
+    ///
+    /// If not set, the machine drives the player on its own entity or, if there is none,
+    /// the first player found among its descendants, as happens with glTF scenes
+    ///
+    /// Example
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_simple_state_machine::*;
+    /// fn spawn_puppet(mut commands: Commands) {
+    ///     let puppet = commands.spawn(AnimationPlayer::default()).id();
+    ///     commands.spawn(
+    ///         AnimationStateMachine::from_states("idle", [AnimationState::empty("idle")], vec![], [])
+    ///             .with_player_entity(puppet),
+    ///     );
+    /// }
+    /// ```
+    pub fn with_player_entity(mut self, player_entity: Entity) -> Self {
+        self.player_entity = Some(player_entity);
+        self
+    }
+
+    /// Adds an entity whose [`AnimationPlayer`] plays the same states as the main player,
+    /// such as the skeleton of a held weapon
+    ///
+    /// The states are started on the additional players when they are entered, from the
+    /// plugin transitions or initialization. Transition and outro clips are only played by
+    /// the main player, the additional ones start the state once these clips finished
+    ///
+    /// Example
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_simple_state_machine::*;
+    /// fn spawn_knight(mut commands: Commands) {
+    ///     let sword = commands.spawn(AnimationPlayer::default()).id();
+    ///     commands.spawn((
+    ///         AnimationPlayer::default(),
+    ///         AnimationStateMachine::from_states("idle", [AnimationState::empty("idle")], vec![], [])
+    ///             .with_additional_player(sword),
+    ///     ));
+    /// }
+    /// ```
+    pub fn with_additional_player(mut self, player_entity: Entity) -> Self {
+        self.additional_players.push(AdditionalPlayer {
+            entity: player_entity,
+            clip_overrides: HashMap::new(),
+        });
+        self
+    }
+
+    /// Plays `clip` instead of the clip of the given state on an additional player
+    ///
+    /// The clip variant selected by the state, if any, is still played instead,
+    /// see [`AnimationState::clip_variants`]
+    ///
+    /// Returns `false` if the entity is not an additional player of the machine,
+    /// see [`AnimationStateMachine::with_additional_player`]
+    pub fn set_additional_player_clip<T: ToString>(
+        &mut self,
+        player_entity: Entity,
+        state_name: T,
+        clip: Handle<AnimationClip>,
+    ) -> bool {
+        match self
+            .additional_players
+            .iter_mut()
+            .find(|player| player.entity == player_entity)
+        {
+            Some(player) => {
+                player.clip_overrides.insert(state_name.to_string(), clip);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Entity holding the [`AnimationPlayer`] driven by the machine
+    ///
+    /// Returns `None` until the player has been set or discovered
+    pub fn player_entity(&self) -> Option<Entity> {
+        self.player_entity
+    }
+
+    /// Sets whether a blending transition can be interrupted by a new transition
+    ///
+    /// This is `true` by default: a new transition starts a fresh blend from the current
+    /// blended pose, and [`AnimationStateMachine::transition_progress`] restarts from `0`.
+    /// If set to `false`, transitions are not evaluated until the current blend completes
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// # use std::time::Duration;
+    /// let mut state_machine = AnimationStateMachine::from_states(
+    ///     "idle",
+    ///     ["idle", "walk"].map(AnimationState::empty),
+    ///     vec![StateMachineTransition::blend(
+    ///         "idle".into(),
+    ///         "walk".into(),
+    ///         StateMachineTrigger::Always,
+    ///         Duration::from_secs(1),
+    ///     )],
+    ///     [],
+    /// );
+    /// state_machine.set_interruptible_blends(false);
+    /// ```
+    pub fn set_interruptible_blends(&mut self, interruptible: bool) {
+        self.interruptible_blends = interruptible;
+    }
+
+    /// Sets whether the plugin skips the evaluation of every transition, `false` by default
+    ///
+    /// Unlike [`AnimationStateMachine::stop`], the current clip keeps playing and follows the
+    /// [`FinishBehavior`] of its state. Transitions can still be taken manually, such as with
+    /// [`AnimationStateMachine::force_transition`]
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let mut state_machine = AnimationStateMachine::from_states(
+    ///     "idle",
+    ///     ["idle", "run"].map(AnimationState::empty),
+    ///     vec![StateMachineTransition::always("idle", "run")],
+    ///     [],
+    /// );
+    /// state_machine.set_lock_transitions(true);
+    /// assert_eq!(state_machine.evaluate_transition(), None);
+    /// state_machine.set_lock_transitions(false);
+    /// assert_eq!(state_machine.evaluate_transition(), Some("run".into()));
+    /// ```
+    pub fn set_lock_transitions(&mut self, lock: bool) {
+        self.lock_transitions = lock;
+    }
+
+    /// Tests if transitions are locked, see [`AnimationStateMachine::set_lock_transitions`]
+    pub fn transitions_locked(&self) -> bool {
+        self.lock_transitions
+    }
+
+    /// Sets whether transitions are only evaluated once their inputs changed, `false` by default
+    ///
+    /// The inputs of [`StateMachineTrigger::Comparison`] transitions are known, see
+    /// [`StateMachineTransition::inputs`]. Once the plugin evaluated the transitions of the
+    /// current state without taking any, those transitions are skipped until one of their
+    /// variables changes, the transitions of the machine change or the state is left.
+    /// Transitions with closure triggers can not be indexed, and are evaluated every frame.
+    /// Only the plugin skips transitions: [`AnimationStateMachine::eligible_transitions`]
+    /// and the preview methods always evaluate all of them
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::prelude::*;
+    /// let fast = StateMachineTrigger::from_comparison(Comparison::new("speed", ComparisonOp::Gt, 3.));
+    /// let mut state_machine = AnimationStateMachine::from_states(
+    ///     "walk",
+    ///     ["walk", "run"].map(AnimationState::empty),
+    ///     vec![StateMachineTransition::new("walk", "run", fast)],
+    ///     [("speed", 1.0.into())],
+    /// );
+    /// state_machine.set_lazy_evaluation(true);
+    /// ```
+    pub fn set_lazy_evaluation(&mut self, lazy: bool) {
+        self.lazy_evaluation = lazy;
+        self.invalidate_transitions();
+    }
+
+    /// Scales the time of the machine, `1` by default
+    ///
+    /// The scale multiplies the time accumulated in each state, used by time based triggers,
+    /// and the progress of blends, so that a scale of `0.5` makes blends last twice as long.
+    /// Negative scales are treated as `0`, pausing the machine time.
+    ///
+    /// While the scale is `0`, the progress of blends stays frozen, but the clips of the
+    /// transitions taken meanwhile are played without crossfade, since the [`AnimationPlayer`]
+    /// can't pause a crossfade while still playing its clips
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let mut state_machine = AnimationStateMachine::from_states("idle", [AnimationState::empty("idle")], vec![], []);
+    /// state_machine.set_time_scale(0.5);
+    /// assert_eq!(state_machine.time_scale(), 0.5);
+    /// state_machine.set_time_scale(-1.);
+    /// assert_eq!(state_machine.time_scale(), 0.);
+    /// ```
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale.max(0.);
+    }
+
+    /// Sets a function redirecting the end state of every transition taken by the plugin
+    ///
+    /// The resolver receives the end state of the selected transition and the variables,
+    /// and returns the state actually entered. Returning [`AnimationStateRef::AnyState`]
+    /// keeps the original end state, while returning an unknown state cancels the transition
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let mut state_machine = AnimationStateMachine::from_states(
+    ///     "idle",
+    ///     ["idle", "attack", "attack_sword"].map(AnimationState::empty),
+    ///     vec![StateMachineTransition::always("idle", "attack")],
+    ///     [("weapon", "sword".into())],
+    /// );
+    /// state_machine.set_transition_resolver(|end_state, variables| match (end_state, variables.get("weapon")) {
+    ///     (AnimationStateRef::StateName(name), Some(StateMachineVariableType::String(weapon))) => {
+    ///         AnimationStateRef::from_string(format!("{name}_{weapon}"))
+    ///     }
+    ///     _ => end_state.to_owned(),
+    /// });
+    /// ```
+    pub fn set_transition_resolver(
+        &mut self,
+        resolver: impl Fn(&AnimationStateRef, &StateMachineVariables) -> AnimationStateRef
+            + Send
+            + Sync
+            + 'static,
+    ) {
+        self.resolver = Some(Arc::new(resolver));
+    }
+
+    /// Removes the function set with [`AnimationStateMachine::set_transition_resolver`]
+    pub fn clear_transition_resolver(&mut self) {
+        self.resolver = None;
+    }
+
+    /// Time scale of the machine, see [`AnimationStateMachine::set_time_scale`]
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    /// Sets whether transitions ending in the current state are ignored
+    ///
+    /// This is `true` by default, so that, for example, an `AnyState -> idle`
+    /// transition does not retrigger `idle` on every frame while the machine is in it.
+    /// If set to `false`, such transitions are applied and emit their [`TransitionEndedEvent`]
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let mut state_machine = AnimationStateMachine::from_states(
+    ///     "hit",
+    ///     ["idle", "hit"].map(AnimationState::empty),
+    ///     vec![StateMachineTransition::from_any("hit", StateMachineTrigger::Always)],
+    ///     [],
+    /// );
+    /// assert_eq!(state_machine.evaluate_transition(), None);
+    /// state_machine.set_suppress_redundant_transitions(false);
+    /// assert_eq!(state_machine.evaluate_transition(), Some("hit".into()));
+    /// ```
+    pub fn set_suppress_redundant_transitions(&mut self, suppress: bool) {
+        self.suppress_redundant_transitions = suppress;
+        self.invalidate_transitions();
+    }
+
+    /// Plays the clip of the current state on the given player
+    ///
+    /// This is done automatically when the machine is added or reset, unless disabled with
+    /// [`SimpleStateMachinePlugin::auto_init`]. The transitions of the machine are only
+    /// evaluated once its current state has been played
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// # use bevy::prelude::*;
+    /// fn play_loaded(
+    ///     mut query: Query<(&mut AnimationStateMachine, &mut AnimationPlayer)>,
+    ///     animations: Res<Assets<AnimationClip>>,
+    /// ) {
+    ///     for (mut state_machine, mut player) in &mut query {
+    ///         state_machine.play_current(&mut player, &animations);
+    ///     }
+    /// }
+    /// ```
+    pub fn play_current(
+        &mut self,
+        player: &mut AnimationPlayer,
+        animations: &Assets<AnimationClip>,
+    ) {
+        self.initialized = true;
+        if let Some(current_state) = self.current_state() {
+            current_state.play(player, animations, None);
+        }
+    }
+
+    /// Time, in seconds, elapsed since the machine entered its current state
+    ///
+    /// This is reset to `0` on every state change
+    pub fn time_in_state(&self) -> f32 {
+        self.time_in_state
+    }
+
+    /// Name of the current state
+    pub fn current_state_name(&self) -> &str {
+        &self.current_state
+    }
+
+    /// Tests if the machine is in the given state
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let state_machine = AnimationStateMachine::new("idle", [], vec![], []);
+    /// assert!(state_machine.is_current_state("idle"));
+    /// assert!(!state_machine.is_current_state("run"));
+    /// ```
+    pub fn is_current_state(&self, name: &str) -> bool {
+        self.current_state == name
+    }
+
+    /// Tests if the machine is in any of the given states
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let state_machine = AnimationStateMachine::new("walk", [], vec![], []);
+    /// assert!(state_machine.is_in_any_of(&["idle", "walk"]));
+    /// assert!(!state_machine.is_in_any_of(&["jump", "fall"]));
+    /// ```
+    pub fn is_in_any_of(&self, names: &[&str]) -> bool {
+        names.contains(&self.current_state_name())
+    }
+
+    /// Tests if the plugin took a transition during the current frame
+    ///
+    /// The flag is cleared each time the plugin evaluates the machine, and set once a
+    /// transition of the machine or of its sub machine is taken
+    ///
+    /// Example
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_simple_state_machine::AnimationStateMachine;
+    /// fn log_changes(query: Query<&AnimationStateMachine>) {
+    ///     for state_machine in query.iter().filter(|state_machine| state_machine.just_changed()) {
+    ///         info!("entered {}", state_machine.current_state_name());
+    ///     }
+    /// }
+    /// ```
+    pub fn just_changed(&self) -> bool {
+        self.just_changed
+    }
+
+    /// State the machine was in before its last transition
+    pub fn previous_state(&self) -> Option<&str> {
+        self.previous_state.as_deref()
+    }
+
+    /// Playback position of the current state clip, from `0` at its start to `1` at its end
+    ///
+    /// This is the time used by the [`AnimationState::markers`]
+    ///
+    /// The [`AnimationState::duration`] of the current state is used, so duration overrides
+    /// are taken into account. Returns `None` if the duration is unknown, as the clip is not loaded
+    ///
+    /// Example
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_simple_state_machine::*;
+    /// let animations = Assets::<AnimationClip>::default();
+    /// let walk = AnimationState::empty("walk").duration_override(2.);
+    /// let state_machine = AnimationStateMachine::new("walk", [("walk", walk)], vec![], []);
+    /// let mut player = AnimationPlayer::default();
+    /// player.seek_to(1.);
+    /// assert_eq!(state_machine.normalized_time(&player, &animations), Some(0.5));
+    /// ```
+    pub fn normalized_time(
+        &self,
+        player: &AnimationPlayer,
+        animations: &Assets<AnimationClip>,
+    ) -> Option<f32> {
+        let duration = self.states.get(&self.current_state)?.duration(animations)?;
+        match duration > 0. {
+            true => Some((player.seek_time() / duration).clamp(0., 1.)),
+            false => Some(1.),
+        }
+    }
+
+    /// Playback time left before the current state clip reaches its end, in seconds
+    ///
+    /// The speed and direction of the player are taken into account, and a paused
+    /// or zero speed player never reaches the end. Like [`AnimationStateMachine::normalized_time`],
+    /// returns `None` if the duration is unknown
+    ///
+    /// Example
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_simple_state_machine::*;
+    /// let animations = Assets::<AnimationClip>::default();
+    /// let attack = AnimationState::empty("attack").duration_override(2.);
+    /// let state_machine = AnimationStateMachine::new("attack", [("attack", attack)], vec![], []);
+    /// let mut player = AnimationPlayer::default();
+    /// player.seek_to(0.5).set_speed(0.5);
+    /// assert_eq!(state_machine.time_remaining(&player, &animations), Some(3.));
+    /// player.set_speed(-2.);
+    /// assert_eq!(state_machine.time_remaining(&player, &animations), Some(0.25));
+    /// ```
+    pub fn time_remaining(
+        &self,
+        player: &AnimationPlayer,
+        animations: &Assets<AnimationClip>,
+    ) -> Option<f32> {
+        let duration = self.states.get(&self.current_state)?.duration(animations)?;
+        let distance = match player.is_playback_reversed() {
+            true => player.seek_time(),
+            false => duration - player.seek_time(),
+        }
+        .max(0.);
+        if distance == 0. {
+            return Some(0.);
+        }
+        match player.is_paused() || player.speed() == 0. {
+            true => Some(f32::INFINITY),
+            false => Some(distance / player.speed().abs()),
+        }
+    }
+
+    /// Progress of the blend currently in execution, in the range `[0, 1]`
+    ///
+    /// Returns `None` if no blending transition is active. On the frame a blend
+    /// completes this returns `Some(1.0)`, and `None` from the next one
+    pub fn transition_progress(&self) -> Option<f32> {
+        self.blend.as_ref().map(ActiveBlend::progress)
+    }
+
+    /// Duration of the blend currently in execution, in seconds
+    ///
+    /// Returns `None` if no blending transition is active, like
+    /// [`AnimationStateMachine::transition_progress`]
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let state_machine = AnimationStateMachine::from_states("idle", [AnimationState::empty("idle")], vec![], []);
+    /// assert_eq!(state_machine.transition_duration(), None);
+    /// ```
+    pub fn transition_duration(&self) -> Option<f32> {
+        self.blend.as_ref().map(|blend| blend.duration)
+    }
+
+    /// Change of the given numeric variable since the previous frame
+    ///
+    /// Returns `None` for non numeric or missing variables
+    pub fn variable_delta(&self, name: &str) -> Option<f32> {
+        self.deltas.get(name).copied()
+    }
+
+    /// Number of times the given state has been entered through a transition
+    ///
+    /// Counters are never reset automatically, see [`AnimationStateMachine::reset_entry_count`]
+    pub fn entry_count(&self, state_name: &str) -> u32 {
+        self.entry_counts
+            .get(state_name)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Resets the entry counter of the given state
+    ///
+    /// This is typically done when leaving a combo chain
+    pub fn reset_entry_count(&mut self, state_name: &str) {
+        self.entry_counts.remove(state_name);
+    }
+
+    /// Finds the entity of the driven [`AnimationPlayer`], caching the result
+    fn resolve_player(
+        &mut self,
+        entity: Entity,
+        players_query: &Query<&mut AnimationPlayer>,
+        children_query: &Query<&Children>,
+    ) -> Option<Entity> {
+        if self.player_entity.is_none() {
+            self.player_entity = find_player(entity, players_query, children_query);
+        }
+        self.player_entity
+    }
+
+    fn trigger_context<'a>(&'a self, variables: &'a StateMachineVariables) -> TriggerContext<'a> {
+        TriggerContext {
+            current_state: &self.current_state,
+            previous_state: self.previous_state.as_deref(),
+            variables,
+            deltas: &self.deltas,
+            entry_counts: &self.entry_counts,
+            time_in_state: self.time_in_state,
+            tick: self.current_tick,
+            last_transition_tick: self.last_transition_tick,
+            blend_completed: self.blend_completed,
+        }
+    }
+
+    /// Advances the child machine of the current state, if any, and its own descendants
+    ///
+    /// Returns the origin state and the transition taken by the deepest child that moved
+    fn update_sub_machine(
+        &mut self,
+        delta: f32,
+        player: &mut AnimationPlayer,
+        animations: &Assets<AnimationClip>,
+        settings: &StateMachineSettings,
+    ) -> Option<(AnimationStateRef, StateMachineTransition)> {
+        let Self {
+            states,
+            variables,
+            current_state,
+            time_scale,
+            ..
+        } = self;
+        let sub_machine = states.get_mut(current_state)?.sub_machine.as_deref_mut()?;
+        sub_machine.variables.clone_from(variables);
+        sub_machine.time_scale = *time_scale;
+        sub_machine.tick(delta);
+        let current_state = sub_machine.current_state()?;
+        if current_state.terminal
+            || (!current_state.interruptible
+                && !animation_finished(player, &current_state, animations, settings.finish_epsilon))
+        {
+            return None;
+        }
+        let Some(transition) = sub_machine
+            .select_transition(variables, settings.multi_match_policy, false)
+            .cloned()
+        else {
+            let taken = sub_machine.update_sub_machine(delta, player, animations, settings);
+            variables.clone_from(&sub_machine.variables);
+            return taken;
+        };
+        let next_state = sub_machine.get_state(transition.end_state.unwrap())?;
+        debug!("triggering {}", transition);
+        sub_machine.enter_state(next_state.name.to_owned());
+        let next_state = sub_machine.states.get_mut(&sub_machine.current_state)?;
+        next_state.select_clip(variables);
+        if let Some(on_take) = &transition.on_take {
+            (on_take)(variables);
+        }
+        next_state.play_from(
+            &current_state,
+            player,
+            animations,
+            scaled_duration(transition.transition_duration, *time_scale),
+        );
+        Some((current_state.state_ref(), transition))
+    }
+
+    /// Loads the clips of the states referencing them by [`AnimationState::clip_path`]
+    fn load_clips(&mut self, asset_server: &AssetServer) {
+        for state in self.states.values_mut() {
+            if let (Some(clip_path), true) = (&state.clip_path, state.clip == Handle::default()) {
+                state.clip = asset_server.load(clip_path.to_owned());
+            }
+            if let Some(sub_machine) = &mut state.sub_machine {
+                sub_machine.load_clips(asset_server);
+            }
+        }
+    }
+
+    /// Starts the playback of the given state on the additional players
+    fn play_additional(
+        &self,
+        state: &AnimationState,
+        players_query: &mut Query<&mut AnimationPlayer>,
+        animations: &Assets<AnimationClip>,
+        transition_duration: Option<Duration>,
+    ) {
+        for additional_player in &self.additional_players {
+            let Ok(mut player) = players_query.get_mut(additional_player.entity) else {
+                continue;
+            };
+            match additional_player.clip_overrides.get(&state.name) {
+                Some(clip) => {
+                    let state = AnimationState {
+                        clip: clip.to_owned(),
+                        ..state.to_owned()
+                    };
+                    state.play(&mut player, animations, transition_duration);
+                }
+                None => state.play(&mut player, animations, transition_duration),
+            }
+        }
+    }
+
+    /// Resolves the clips of the states referencing them by [`AnimationState::library_clip`]
+    fn resolve_library_clips(&mut self, library: &ClipLibrary) {
+        for state in self.states.values_mut() {
+            if let (Some(clip_name), true) = (&state.library_clip, state.clip == Handle::default())
+            {
+                match library.get(clip_name) {
+                    Some(clip) => state.clip = clip.to_owned(),
+                    None => warn!(
+                        "clip {} of state {} is missing from the clip library",
+                        clip_name, state.name
+                    ),
+                }
+            }
+            if let Some(sub_machine) = &mut state.sub_machine {
+                sub_machine.resolve_library_clips(library);
+            }
+        }
+    }
+
+    /// Moves to the state selected by the entry transitions, if any
+    fn choose_entry_state(&mut self) {
+        let context = self.trigger_context(&self.variables);
+        let Some(entry_state) = self
+            .entry_transitions
+            .iter()
+            .find(|transition| {
+                self.states.contains_key(transition.end_state.unwrap())
+                    && transition.is_enabled(&self.variables)
+                    && transition.fires(&context)
+            })
+            .map(|transition| transition.end_state.unwrap().to_owned())
+        else {
+            return;
+        };
+        self.current_state = entry_state;
+        self.time_in_state = 0.;
+        self.marker_time = None;
+    }
+
+    fn record_transition(&mut self, record: TransitionRecord, capacity: usize) {
+        if capacity == 0 {
+            return;
+        }
+        if self.history.len() >= capacity {
+            self.history.drain(..=self.history.len() - capacity);
+        }
+        self.history.push(record);
+    }
+
+    fn tick(&mut self, delta: f32) {
+        self.time_in_state += delta;
+        let values: HashMap<String, f32> = self
+            .variables
+            .iter()
+            .filter_map(|(name, var)| Some((name.to_owned(), var.as_f32()?)))
+            .collect();
+        self.deltas = values
+            .iter()
+            .map(|(name, value)| {
+                let previous = self.previous_values.get(name).unwrap_or(value);
+                (name.to_owned(), value - previous)
+            })
+            .collect();
+        self.previous_values = values;
+        self.blend_completed = false;
+        if let Some(blend) = &mut self.blend {
+            if blend.elapsed >= blend.duration {
+                self.blend = None;
+                self.blend_completed = true;
+            } else {
+                blend.elapsed = (blend.elapsed + delta).min(blend.duration);
+            }
+        }
+    }
+
+    fn enter_state(&mut self, state_name: String) {
+        if let Some(sub_machine) = self
+            .states
+            .get_mut(&self.current_state)
+            .and_then(|state| state.sub_machine.as_deref_mut())
+        {
+            sub_machine.reset();
+        }
+        *self.entry_counts.entry(state_name.to_owned()).or_default() += 1;
+        self.previous_state = Some(std::mem::replace(&mut self.current_state, state_name));
+        self.time_in_state = 0.;
+        self.marker_time = None;
+        self.transition_clips.clear();
+        self.invalidate_transitions();
+        self.waiting_for_clip = false;
+        self.select_clip();
+    }
+
+    /// Applies the transition resolver, if any, to the end state of a selected transition
+    fn resolve_end_state(&self, end_state: &AnimationStateRef) -> String {
+        let resolved = self
+            .resolver
+            .as_ref()
+            .map(|resolver| resolver(end_state, &self.variables));
+        match resolved {
+            Some(AnimationStateRef::StateName(state_name)) => state_name,
+            _ => end_state.unwrap().to_owned(),
+        }
+    }
+
+    /// Chooses the clip variant of the current state, see [`AnimationState::clip_variants`]
+    fn select_clip(&mut self) {
+        let Self {
+            states,
+            variables,
+            current_state,
+            ..
+        } = self;
+        if let Some(state) = states.get_mut(current_state) {
+            state.select_clip(variables);
+        }
+    }
+
+    /// Tests if the plugin skips the evaluation of transitions from the current state
+    ///
+    /// Non interruptible states are known to wait for their clip as of the last update
+    fn transitions_blocked(&self) -> bool {
+        self.stopped
+            || self.lock_transitions
+            || self.waiting_for_clip
+            || !self.transition_clips.is_empty()
+            || (!self.interruptible_blends && self.blend.is_some())
+            || self
+                .states
+                .get(&self.current_state)
+                .is_some_and(|state| state.terminal)
+    }
+
+    /// Chooses the transition to take from the current state, according to `policy`
+    ///
+    /// If `lazy`, the transitions settled since the last evaluation are skipped,
+    /// see [`AnimationStateMachine::set_lazy_evaluation`]
+    fn select_transition(
+        &self,
+        variables: &StateMachineVariables,
+        policy: MultiMatchPolicy,
+        lazy: bool,
+    ) -> Option<&StateMachineTransition> {
+        if self
+            .states
+            .get(&self.current_state)
+            .is_some_and(|state| state.terminal)
+        {
+            return None;
+        }
+        let mut candidates = self
+            .indexed_transitions_from(&self.current_state)
+            .into_iter()
+            .filter(|(index, transition)| !(lazy && self.is_settled(*index, transition)))
+            .map(|(_, transition)| transition)
+            .filter(|transition| self.can_take(transition, variables));
+        match policy {
+            MultiMatchPolicy::FirstMatch => candidates.next(),
+            MultiMatchPolicy::HighestPriority => {
+                candidates.reduce(
+                    |best, transition| match transition.priority > best.priority {
+                        true => transition,
+                        false => best,
+                    },
+                )
+            }
+            MultiMatchPolicy::WarnAndFirst => {
+                let first = candidates.next();
+                if let (Some(first), Some(other)) = (&first, candidates.next()) {
+                    warn!(
+                        "ambiguous transitions from {}: {} and {} both match, taking the first",
+                        self.current_state, first, other
+                    );
+                }
+                first
+            }
+        }
+    }
+
+    /// Tests if `transition` would be taken with the given variables
+    fn can_take(
+        &self,
+        transition: &StateMachineTransition,
+        variables: &StateMachineVariables,
+    ) -> bool {
+        let Some(next_state) = self.states.get(transition.end_state.unwrap()) else {
+            return false;
+        };
+        transition.is_enabled(variables)
+            && !(self.suppress_redundant_transitions && next_state.name == self.current_state)
+            && transition.fires(&self.trigger_context(variables))
+    }
+
+    /// Tests if the transition at `index` was not taken by the last evaluation of the
+    /// current state, and none of its inputs changed since
+    fn is_settled(&self, index: usize, transition: &StateMachineTransition) -> bool {
+        self.transitions_settled
+            && matches!(transition.trigger, StateMachineTrigger::Comparison(_))
+            && !self.dirty_transitions.contains(&index)
+    }
+
+    /// Indexes the transitions by the variables they read, see [`StateMachineTransition::inputs`]
+    ///
+    /// Transitions are numbered in `transitions` then `global_transitions` order
+    fn index_transitions(&mut self) {
+        self.transition_inputs.clear();
+        for (index, transition) in self
+            .transitions
+            .iter()
+            .chain(&self.global_transitions)
+            .enumerate()
+        {
+            for name in transition.inputs().into_iter().flatten() {
+                self.transition_inputs
+                    .entry(name.to_owned())
+                    .or_default()
+                    .push(index);
+            }
+        }
+        // marking transitions dirty never allocates
+        self.dirty_transitions =
+            HashSet::with_capacity(self.transitions.len() + self.global_transitions.len());
+        self.invalidate_transitions();
+    }
+
+    /// Makes the plugin evaluate every transition of the current state on its next update
+    fn invalidate_transitions(&mut self) {
+        self.transitions_settled = false;
+        self.dirty_transitions.clear();
+    }
+
+    /// Marks the transitions reading the given variable for evaluation on the next update
+    fn mark_dirty(&mut self, name: &str) {
+        if let Some(indices) = self.transition_inputs.get(name) {
+            self.dirty_transitions.extend(indices);
+        }
+    }
+
+    #[inline]
+    fn current_state(&self) -> Option<AnimationState> {
         self.get_state(&self.current_state)
     }
 
-    fn get_state(&self, state_name: &String) -> Option<AnimationState> {
-        match self.states.contains_key(state_name) {
-            true => Some(self.states[state_name].to_owned()),
-            false => None,
+    fn get_state(&self, state_name: &String) -> Option<AnimationState> {
+        match self.states.contains_key(state_name) {
+            true => Some(self.states[state_name].to_owned()),
+            false => None,
+        }
+    }
+
+    /// Lists the transitions from the current state whose trigger currently fires,
+    /// in evaluation order
+    ///
+    /// Unlike [`AnimationStateMachine::evaluate_transition`], the [`MultiMatchPolicy`]
+    /// and terminal states are not taken into account
+    pub fn eligible_transitions(&self) -> Vec<&StateMachineTransition> {
+        self.transitions_from(&self.current_state)
+            .into_iter()
+            .filter(|transition| self.can_take(transition, &self.variables))
+            .collect()
+    }
+
+    /// Lists the eligible transitions which are not global
+    ///
+    /// See [`AnimationStateMachine::eligible_transitions`]
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let state_machine = AnimationStateMachine::new(
+    ///     "idle",
+    ///     [
+    ///         ("idle", AnimationState::empty("idle")),
+    ///         ("run", AnimationState::empty("run")),
+    ///         ("jump", AnimationState::empty("jump")),
+    ///         ("dead", AnimationState::empty("dead")),
+    ///         ("hurt", AnimationState::empty("hurt")),
+    ///     ],
+    ///     vec![
+    ///         StateMachineTransition::always("idle", "run"),
+    ///         StateMachineTransition::never("idle", "jump"),
+    ///     ],
+    ///     [],
+    /// )
+    /// .with_global_transitions(vec![
+    ///     StateMachineTransition::from_any("dead", StateMachineTrigger::Always),
+    ///     StateMachineTransition::from_any("hurt", StateMachineTrigger::Never),
+    /// ]);
+    /// let ends = |transitions: Vec<&StateMachineTransition>| -> Vec<String> {
+    ///     transitions.iter().map(|transition| transition.end_state.to_string()).collect()
+    /// };
+    /// assert_eq!(ends(state_machine.eligible_local()), ["run"]);
+    /// assert_eq!(ends(state_machine.eligible_global()), ["dead"]);
+    /// assert_eq!(ends(state_machine.eligible_transitions()), ["run", "dead"]);
+    /// ```
+    pub fn eligible_local(&self) -> Vec<&StateMachineTransition> {
+        self.transitions
+            .iter()
+            .filter(|transition| match &transition.start_state {
+                AnimationStateRef::AnyState => true,
+                AnimationStateRef::StateName(start) => *start == self.current_state,
+            })
+            .filter(|transition| self.can_take(transition, &self.variables))
+            .collect()
+    }
+
+    /// Lists the eligible global transitions
+    ///
+    /// See [`AnimationStateMachine::eligible_transitions`] and [`AnimationStateMachine::with_global_transitions`]
+    pub fn eligible_global(&self) -> Vec<&StateMachineTransition> {
+        self.global_transitions
+            .iter()
+            .filter(|transition| self.can_take(transition, &self.variables))
+            .collect()
+    }
+
+    /// Gets the state with the given name
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let state_machine = AnimationStateMachine::new(
+    ///     "idle",
+    ///     [("idle", AnimationState::empty("idle"))],
+    ///     vec![],
+    ///     [],
+    /// );
+    /// assert!(state_machine.state("idle").is_some_and(|idle| idle.interruptible));
+    /// assert!(state_machine.state("run").is_none());
+    /// ```
+    pub fn state(&self, name: &str) -> Option<&AnimationState> {
+        self.states.get(name)
+    }
+
+    /// Lists the transitions that can be taken from the given state, in evaluation order,
+    /// including the ones starting from [`AnimationStateRef::AnyState`] and the global ones
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let state_machine = AnimationStateMachine::new(
+    ///     "idle",
+    ///     [],
+    ///     vec![
+    ///         StateMachineTransition::always("idle", "run"),
+    ///         StateMachineTransition::always("run", "idle"),
+    ///         StateMachineTransition::from_any("dead", StateMachineTrigger::Never),
+    ///     ],
+    ///     [],
+    /// );
+    /// let ends: Vec<_> = state_machine
+    ///     .transitions_from("idle")
+    ///     .iter()
+    ///     .map(|transition| transition.end_state.to_string())
+    ///     .collect();
+    /// assert_eq!(ends, ["run", "dead"]);
+    /// ```
+    pub fn transitions_from(&self, state_name: &str) -> Vec<&StateMachineTransition> {
+        self.indexed_transitions_from(state_name)
+            .into_iter()
+            .map(|(_, transition)| transition)
+            .collect()
+    }
+
+    /// Like [`AnimationStateMachine::transitions_from`], along with the index of each transition
+    fn indexed_transitions_from(&self, state_name: &str) -> Vec<(usize, &StateMachineTransition)> {
+        let local = self
+            .transitions
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| match &t.start_state {
+                AnimationStateRef::AnyState => true,
+                AnimationStateRef::StateName(start) => start == state_name,
+            });
+        let global = self
+            .global_transitions
+            .iter()
+            .enumerate()
+            .map(|(index, transition)| (self.transitions.len() + index, transition));
+        match self.global_transitions_order {
+            GlobalTransitionsOrder::BeforeLocal => global.chain(local).collect(),
+            GlobalTransitionsOrder::AfterLocal => local.chain(global).collect(),
+        }
+    }
+
+    /// Compares two machines, ignoring the trigger functions
+    ///
+    /// States, variables and the current state must be equal, while transitions
+    /// are compared by their fields and by their trigger, see [`StateMachineTrigger::kind`].
+    /// Closure triggers only need to be of the same kind, [`StateMachineTrigger::Comparison`]
+    /// must be equal
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let build = || AnimationStateMachine::new(
+    ///     "idle",
+    ///     [("idle", AnimationState::empty("idle")), ("run", AnimationState::empty("run"))],
+    ///     vec![StateMachineTransition::immediate(
+    ///         AnimationStateRef::from_string("idle"),
+    ///         AnimationStateRef::from_string("run"),
+    ///         StateMachineTrigger::from(|vars| vars.get("run").is_some_and(|run| run.is_bool(true))),
+    ///     )],
+    ///     [("run", StateMachineVariableType::Bool(false))],
+    /// );
+    /// let mut state_machine = build();
+    /// assert!(state_machine.structural_eq(&build()));
+    /// state_machine.update_variable("run", StateMachineVariableType::Bool(true));
+    /// assert!(!state_machine.structural_eq(&build()));
+    ///
+    /// let with_comparison = |comparison| AnimationStateMachine::from_states(
+    ///     "idle",
+    ///     ["idle", "run"].map(AnimationState::empty),
+    ///     vec![StateMachineTransition::new("idle", "run", StateMachineTrigger::from_comparison(comparison))],
+    ///     [("speed", 0.0.into())],
+    /// );
+    /// let fast = with_comparison(Comparison::new("speed", ComparisonOp::Gt, 3.0));
+    /// assert!(fast.structural_eq(&with_comparison(Comparison::new("speed", ComparisonOp::Gt, 3.0))));
+    /// assert!(!fast.structural_eq(&with_comparison(Comparison::new("speed", ComparisonOp::Gt, 5.0))));
+    /// ```
+    pub fn structural_eq(&self, other: &Self) -> bool {
+        self.current_state == other.current_state
+            && self.states == other.states
+            && self.variables == other.variables
+            && transitions_structural_eq(&self.transitions, &other.transitions)
+            && transitions_structural_eq(&self.entry_transitions, &other.entry_transitions)
+            && transitions_structural_eq(&self.global_transitions, &other.global_transitions)
+            && self.global_transitions_order == other.global_transitions_order
+    }
+
+    /// Tests if the state `to` can be reached from the state `from` through any chain of transitions
+    ///
+    /// Transitions starting from [`AnimationStateRef::AnyState`] are followed from every state.
+    /// A state is always reachable from itself
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let state_machine = AnimationStateMachine::new(
+    ///     "idle",
+    ///     ["idle", "run", "dead", "menu"].map(|name| (name, AnimationState::empty(name))),
+    ///     vec![
+    ///         StateMachineTransition::always("idle", "run"),
+    ///         StateMachineTransition::from_any("dead", StateMachineTrigger::Never),
+    ///     ],
+    ///     [],
+    /// );
+    /// assert!(state_machine.can_reach("idle", "run"));
+    /// assert!(state_machine.can_reach("run", "dead"));
+    /// assert!(!state_machine.can_reach("run", "idle"));
+    /// assert!(!state_machine.can_reach("idle", "menu"));
+    /// ```
+    pub fn can_reach(&self, from: &str, to: &str) -> bool {
+        if !self.states.contains_key(from) || !self.states.contains_key(to) {
+            return false;
+        }
+        let mut visited = HashSet::from([from]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(state_name) = queue.pop_front() {
+            if state_name == to {
+                return true;
+            }
+            for transition in self.transitions.iter().chain(&self.global_transitions) {
+                let starts_here = match &transition.start_state {
+                    AnimationStateRef::AnyState => true,
+                    AnimationStateRef::StateName(start) => start == state_name,
+                };
+                if let (true, AnimationStateRef::StateName(end)) =
+                    (starts_here, &transition.end_state)
+                {
+                    if self.states.contains_key(end) && visited.insert(end) {
+                        queue.push_back(end);
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// Number of transitions leaving each state, sorted by state name
+    ///
+    /// Transitions from [`AnimationStateRef::AnyState`] and global transitions are counted for
+    /// every state, except those ending in the state itself while redundant transitions
+    /// are suppressed, see [`AnimationStateMachine::set_suppress_redundant_transitions`]
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let state_machine = AnimationStateMachine::from_states(
+    ///     "idle",
+    ///     ["idle", "walk", "run", "dead"].map(AnimationState::empty),
+    ///     vec![
+    ///         StateMachineTransition::always("idle", "walk"),
+    ///         StateMachineTransition::always("walk", "idle"),
+    ///         StateMachineTransition::always("walk", "run"),
+    ///         StateMachineTransition::from_any("dead", StateMachineTrigger::Never),
+    ///     ],
+    ///     [],
+    /// );
+    /// assert_eq!(
+    ///     state_machine.connectivity(),
+    ///     [("dead".to_owned(), 0), ("idle".to_owned(), 2), ("run".to_owned(), 1), ("walk".to_owned(), 3)],
+    /// );
+    /// ```
+    pub fn connectivity(&self) -> Vec<(String, usize)> {
+        let mut connectivity: Vec<_> = self
+            .states
+            .keys()
+            .map(|state_name| {
+                let count = self
+                    .transitions_from(state_name)
+                    .into_iter()
+                    .filter(|transition| {
+                        !(self.suppress_redundant_transitions
+                            && transition.start_state == AnimationStateRef::AnyState
+                            && transition.end_state.unwrap() == state_name)
+                    })
+                    .count();
+                (state_name.to_owned(), count)
+            })
+            .collect();
+        connectivity.sort();
+        connectivity
+    }
+
+    /// Tests if the starting state and every named transition endpoint exist
+    ///
+    /// Sub machines are checked as well
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let state_machine = |transition| {
+    ///     AnimationStateMachine::new("idle", [("idle", AnimationState::empty("idle"))], vec![transition], [])
+    /// };
+    /// assert!(state_machine(StateMachineTransition::from_any("idle", StateMachineTrigger::Never)).is_valid());
+    /// assert!(!state_machine(StateMachineTransition::always("idle", "run")).is_valid());
+    /// ```
+    pub fn is_valid(&self) -> bool {
+        let exists = |state_ref: &AnimationStateRef| match state_ref {
+            AnimationStateRef::AnyState => true,
+            AnimationStateRef::StateName(state_name) => self.states.contains_key(state_name),
+        };
+        self.states.contains_key(&self.starting_state)
+            && self
+                .transitions
+                .iter()
+                .chain(&self.entry_transitions)
+                .chain(&self.global_transitions)
+                .all(|transition| exists(&transition.start_state) && exists(&transition.end_state))
+            && self
+                .states
+                .values()
+                .filter_map(|state| state.sub_machine.as_deref())
+                .all(AnimationStateMachine::is_valid)
+    }
+
+    /// Gets the value of the given variable
+    pub fn variable(&self, name: &str) -> Option<&StateMachineVariableType> {
+        self.variables.get(name)
+    }
+
+    /// Variables of the machine
+    pub fn variables(&self) -> &StateMachineVariables {
+        &self.variables
+    }
+
+    /// Predicts the state the machine would move to if the given variables were set
+    ///
+    /// The overrides are applied to a copy of the variables, and the end state of the
+    /// transition that would be taken from the current state is returned, see
+    /// [`AnimationStateMachine::next_transition`].
+    /// Neither the machine nor its [`AnimationPlayer`] are modified
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let state_machine = AnimationStateMachine::new(
+    ///     "idle",
+    ///     [
+    ///         ("idle", AnimationState::empty("idle")),
+    ///         ("run", AnimationState::empty("run")),
+    ///     ],
+    ///     vec![StateMachineTransition::immediate(
+    ///         "idle".into(),
+    ///         "run".into(),
+    ///         StateMachineTrigger::from(|vars| vars.get("run").is_some_and(|run| run.is_bool(true))),
+    ///     )],
+    ///     [("run", StateMachineVariableType::Bool(false))],
+    /// );
+    /// let overrides = state_machine_variables([("run", StateMachineVariableType::Bool(true))]);
+    /// assert_eq!(state_machine.preview_transition(&overrides), Some("run".into()));
+    /// assert_eq!(state_machine.preview_transition(&StateMachineVariables::default()), None);
+    /// assert!(state_machine.variable("run").is_some_and(|run| run.is_bool(false)));
+    /// ```
+    pub fn preview_transition(
+        &self,
+        overrides: &StateMachineVariables,
+    ) -> Option<AnimationStateRef> {
+        let mut variables = self.variables.to_owned();
+        variables.extend(
+            overrides
+                .iter()
+                .map(|(name, value)| (name.to_owned(), value.to_owned())),
+        );
+        if self.transitions_blocked() {
+            return None;
+        }
+        self.select_transition(&variables, self.multi_match_policy, false)
+            .map(|transition| transition.end_state.to_owned())
+    }
+
+    /// Evaluates the transitions from the current state, returning the end state
+    /// of the one that would be taken, see [`AnimationStateMachine::next_transition`]
+    ///
+    /// Together with [`AnimationStateMachine::apply_transition`], this allows testing
+    /// the logic of a machine without an [`App`] or an [`AnimationPlayer`]
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let mut state_machine = AnimationStateMachine::new(
+    ///     "idle",
+    ///     [
+    ///         ("idle", AnimationState::empty("idle")),
+    ///         ("run", AnimationState::empty("run")),
+    ///     ],
+    ///     vec![
+    ///         StateMachineTransition::always("idle", "run"),
+    ///         StateMachineTransition::always("run", "idle"),
+    ///     ],
+    ///     [],
+    /// );
+    /// let next_state = state_machine.evaluate_transition().unwrap();
+    /// assert_eq!(next_state, "run".into());
+    /// state_machine.apply_transition(&next_state);
+    /// assert_eq!(state_machine.evaluate_transition(), Some("idle".into()));
+    /// assert_eq!(state_machine.entry_count("run"), 1);
+    /// ```
+    ///
+    /// A trigger that panics is logged and treated as not firing, so other transitions
+    /// are still evaluated
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let state_machine = AnimationStateMachine::new(
+    ///     "idle",
+    ///     [
+    ///         ("idle", AnimationState::empty("idle")),
+    ///         ("run", AnimationState::empty("run")),
+    ///         ("jump", AnimationState::empty("jump")),
+    ///     ],
+    ///     vec![
+    ///         StateMachineTransition::immediate(
+    ///             "idle".into(),
+    ///             "jump".into(),
+    ///             StateMachineTrigger::from(|vars| vars["missing"].is_bool(true)),
+    ///         ),
+    ///         StateMachineTransition::always("idle", "run"),
+    ///     ],
+    ///     [],
+    /// );
+    /// assert_eq!(state_machine.evaluate_transition(), Some("run".into()));
+    /// ```
+    pub fn evaluate_transition(&self) -> Option<AnimationStateRef> {
+        self.next_transition()
+            .map(|transition| transition.end_state.to_owned())
+    }
+
+    /// Transition that would be taken from the current state with the current variables
+    ///
+    /// Like [`AnimationStateMachine::evaluate_transition`], but returns the whole transition.
+    /// The transition is chosen by the [`MultiMatchPolicy`] of the plugin, or the default one
+    /// before the machine is first updated. Nothing is returned while the plugin would not
+    /// evaluate transitions: the machine is stopped or its transitions are locked, a transition
+    /// clip or an uninterruptible blend is playing, the current state is terminal, or it is
+    /// non interruptible and its clip had not finished on the last update
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let mut state_machine = AnimationStateMachine::from_states(
+    ///     "idle",
+    ///     ["idle", "walk", "run"].map(AnimationState::empty),
+    ///     vec![
+    ///         StateMachineTransition::new("idle", "walk", StateMachineTrigger::from_comparison(Comparison::new("speed", ComparisonOp::Gt, 0.0)))
+    ///             .with_tag("start_walking"),
+    ///         StateMachineTransition::new("idle", "run", StateMachineTrigger::from_comparison(Comparison::new("speed", ComparisonOp::Gt, 5.0)))
+    ///             .with_priority(1)
+    ///             .with_tag("start_running"),
+    ///     ],
+    ///     [("speed", 0.0.into())],
+    /// );
+    /// assert!(state_machine.next_transition().is_none());
+    /// state_machine.update_variable("speed", 1.0.into());
+    /// let transition = state_machine.next_transition().unwrap();
+    /// assert_eq!(transition.end_state, "walk".into());
+    /// assert_eq!(transition.tag.as_deref(), Some("start_walking"));
+    /// ```
+    pub fn next_transition(&self) -> Option<&StateMachineTransition> {
+        if self.transitions_blocked() {
+            return None;
+        }
+        self.select_transition(&self.variables, self.multi_match_policy, false)
+    }
+
+    /// Moves the machine to the given state, as if a transition to it was taken
+    ///
+    /// This bypasses animation playback, and ignores [`AnimationStateRef::AnyState`]
+    /// and unknown states. No [`TransitionEndedEvent`] is sent.
+    /// A stopped machine is reactivated, and the plugin plays the clip of the new state
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let mut state_machine = AnimationStateMachine::from_states(
+    ///     "idle",
+    ///     ["idle", "dead"].map(AnimationState::empty),
+    ///     vec![],
+    ///     [],
+    /// );
+    /// state_machine.stop();
+    /// state_machine.apply_transition(&"dead".into());
+    /// assert!(state_machine.is_current_state("dead"));
+    /// assert!(!state_machine.is_stopped());
+    /// ```
+    pub fn apply_transition(&mut self, to: &AnimationStateRef) {
+        let AnimationStateRef::StateName(state_name) = to else {
+            return;
+        };
+        if self.states.contains_key(state_name) {
+            self.enter_state(state_name.to_owned());
+            self.blend = None;
+            self.last_transition_tick = self.current_tick;
+            if self.stopped {
+                self.stopped = false;
+                self.replay = true;
+            }
+        }
+    }
+
+    /// Moves the machine to the given state like [`AnimationStateMachine::apply_transition`],
+    /// reporting why the move is refused
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let mut state_machine = AnimationStateMachine::from_states(
+    ///     "idle",
+    ///     [
+    ///         AnimationState::empty("idle"),
+    ///         AnimationState::empty("stun"),
+    ///         AnimationState::empty("dead").terminal(true),
+    ///     ],
+    ///     vec![],
+    ///     [],
+    /// );
+    /// assert_eq!(
+    ///     state_machine.force_transition("fly"),
+    ///     Err(ForceTransitionError::UnknownState("fly".to_owned())),
+    /// );
+    /// assert_eq!(state_machine.force_transition("idle"), Err(ForceTransitionError::SameState));
+    /// assert_eq!(state_machine.force_transition("stun"), Ok(()));
+    /// assert_eq!(state_machine.force_transition("dead"), Ok(()));
+    /// assert_eq!(state_machine.force_transition("idle"), Err(ForceTransitionError::Terminal));
+    /// ```
+    pub fn force_transition(
+        &mut self,
+        to: impl Into<AnimationStateRef>,
+    ) -> Result<(), ForceTransitionError> {
+        let to = to.into();
+        let state_name = match &to {
+            AnimationStateRef::StateName(state_name) if self.states.contains_key(state_name) => {
+                state_name
+            }
+            _ => return Err(ForceTransitionError::UnknownState(to.to_string())),
+        };
+        if *state_name == self.current_state {
+            return Err(ForceTransitionError::SameState);
+        }
+        if self
+            .states
+            .get(&self.current_state)
+            .is_some_and(|state| state.terminal)
+        {
+            return Err(ForceTransitionError::Terminal);
+        }
+        self.apply_transition(&to);
+        Ok(())
+    }
+
+    /// Lists the name and current type of every variable, sorted by name
+    ///
+    /// The type is the name of the [`StateMachineVariableType`] variant, e.g. `"Bool"` or `"F32"`
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let state_machine = AnimationStateMachine::new(
+    ///     "idle",
+    ///     [],
+    ///     vec![],
+    ///     [
+    ///         ("run", StateMachineVariableType::Bool(false)),
+    ///         ("speed", StateMachineVariableType::F32(0.)),
+    ///     ],
+    /// );
+    /// assert_eq!(
+    ///     state_machine.variable_schema(),
+    ///     vec![("run".to_string(), "Bool"), ("speed".to_string(), "F32")],
+    /// );
+    /// ```
+    pub fn variable_schema(&self) -> Vec<(String, &'static str)> {
+        let mut schema: Vec<_> = self
+            .variables
+            .iter()
+            .map(|(name, value)| (name.to_owned(), value.type_name()))
+            .collect();
+        schema.sort_unstable();
+        schema
+    }
+
+    /// Gets a mutable reference to the value of the given variable
+    ///
+    /// This allows tools to edit values in place
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let mut state_machine = AnimationStateMachine::new(
+    ///     "idle",
+    ///     [],
+    ///     vec![],
+    ///     [("speed", StateMachineVariableType::F32(0.))],
+    /// );
+    /// if let Some(StateMachineVariableType::F32(speed)) = state_machine.variable_mut("speed") {
+    ///     *speed += 1.5;
+    /// }
+    /// assert!(state_machine.variable("speed").is_some_and(|speed| speed.is_f32(1.5)));
+    /// ```
+    pub fn variable_mut(&mut self, name: &str) -> Option<&mut StateMachineVariableType> {
+        self.mark_dirty(name);
+        self.variables.get_mut(name)
+    }
+
+    /// Updates the value of the given variable, returning its previous value
+    ///
+    /// Numeric values are clamped or wrapped if the variable has been declared with
+    /// [`AnimationStateMachine::declare_variable_clamped`] or [`AnimationStateMachine::declare_variable_wrapped`]
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let mut state_machine = AnimationStateMachine::new("idle", [], vec![], []);
+    /// assert_eq!(state_machine.update_variable("run", StateMachineVariableType::Bool(false)), None);
+    /// assert_eq!(
+    ///     state_machine.update_variable("run", StateMachineVariableType::Bool(true)),
+    ///     Some(StateMachineVariableType::Bool(false)),
+    /// );
+    /// ```
+    pub fn update_variable<T: ToString>(
+        &mut self,
+        name: T,
+        value: StateMachineVariableType,
+    ) -> Option<StateMachineVariableType> {
+        let name = name.to_string();
+        let value = match self.constraints.get(&name) {
+            Some(constraint) => constraint.apply(value),
+            None => value,
+        };
+        if self.variables.get(&name) != Some(&value) {
+            self.mark_dirty(&name);
+        }
+        self.variables.insert(name, value)
+    }
+
+    /// Updates every variable of the given profile, such as a preset loaded from an asset
+    ///
+    /// Variables missing from the machine are added, and variables missing from the
+    /// profile keep their value. Constraints are applied like in [`AnimationStateMachine::update_variable`]
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let mut state_machine = AnimationStateMachine::new(
+    ///     "idle",
+    ///     [],
+    ///     vec![],
+    ///     [("speed", 1.0.into()), ("armed", false.into())],
+    /// );
+    /// state_machine.apply_variables(state_machine_variables([("speed", 2.0.into()), ("rage", 10.into())]));
+    /// assert_eq!(state_machine.variable("speed"), Some(&StateMachineVariableType::F32(2.)));
+    /// assert_eq!(state_machine.variable("armed"), Some(&StateMachineVariableType::Bool(false)));
+    /// assert_eq!(state_machine.variable("rage"), Some(&StateMachineVariableType::I32(10)));
+    /// ```
+    pub fn apply_variables(&mut self, variables: StateMachineVariables) {
+        for (name, value) in variables {
+            self.update_variable(name, value);
+        }
+    }
+
+    /// Updates the value of the given variable, returning its previous value
+    ///
+    /// Behaves like [`AnimationStateMachine::update_variable`], but only allocates the name
+    /// of the variable when it is inserted for the first time, which suits per frame updates
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let mut state_machine = AnimationStateMachine::new(
+    ///     "idle",
+    ///     [],
+    ///     vec![],
+    ///     [("speed", StateMachineVariableType::F32(0.))],
+    /// );
+    /// for frame in 1..=3 {
+    ///     state_machine.update_variable_str("speed", StateMachineVariableType::F32(frame as f32));
+    /// }
+    /// assert!(state_machine.variable("speed").is_some_and(|speed| speed.is_f32(3.)));
+    /// assert_eq!(state_machine.update_variable_str("run", StateMachineVariableType::Bool(true)), None);
+    /// ```
+    pub fn update_variable_str(
+        &mut self,
+        name: &str,
+        value: StateMachineVariableType,
+    ) -> Option<StateMachineVariableType> {
+        let value = match self.constraints.get(name) {
+            Some(constraint) => constraint.apply(value),
+            None => value,
+        };
+        if self.variables.get(name) != Some(&value) {
+            self.mark_dirty(name);
+        }
+        match self.variables.get_mut(name) {
+            Some(current) => Some(std::mem::replace(current, value)),
+            None => self.variables.insert(name.to_owned(), value),
+        }
+    }
+
+    /// Sets the given [`StateMachineVariableType::Trigger`] variable
+    ///
+    /// The trigger stays set until it is consumed by a [`StateMachineTransition::on_trigger`]
+    /// transition or cleared with [`AnimationStateMachine::clear_triggers`]
+    pub fn set_trigger(&mut self, name: &str) {
+        self.update_variable_str(name, StateMachineVariableType::Trigger(true));
+    }
+
+    /// Resets every [`StateMachineVariableType::Trigger`] variable, discarding the pending ones
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let mut state_machine = AnimationStateMachine::new(
+    ///     "idle",
+    ///     [
+    ///         ("idle", AnimationState::empty("idle")),
+    ///         ("jump", AnimationState::empty("jump")),
+    ///     ],
+    ///     vec![StateMachineTransition::on_trigger("idle", "jump", "jump")],
+    ///     [("jump", StateMachineVariableType::Trigger(false))],
+    /// );
+    /// state_machine.set_trigger("jump");
+    /// assert_eq!(state_machine.evaluate_transition(), Some("jump".into()));
+    /// state_machine.clear_triggers();
+    /// assert_eq!(state_machine.evaluate_transition(), None);
+    /// assert_eq!(state_machine.variable("jump"), Some(&StateMachineVariableType::Trigger(false)));
+    /// ```
+    pub fn clear_triggers(&mut self) {
+        for value in self.variables.values_mut() {
+            if let StateMachineVariableType::Trigger(set) = value {
+                *set = false;
+            }
+        }
+        self.invalidate_transitions();
+    }
+
+    /// Keeps the given numeric variable within `min..=max` when updated
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let mut state_machine = AnimationStateMachine::new(
+    ///     "idle",
+    ///     [],
+    ///     vec![],
+    ///     [("blend", StateMachineVariableType::F32(0.))],
+    /// );
+    /// state_machine.declare_variable_clamped("blend", 0., 1.);
+    /// state_machine.update_variable("blend", StateMachineVariableType::F32(1.5));
+    /// assert!(state_machine.variable("blend").is_some_and(|blend| blend.is_f32(1.)));
+    /// ```
+    pub fn declare_variable_clamped<T: ToString>(&mut self, name: T, min: f32, max: f32) {
+        self.declare_constraint(name.to_string(), VariableConstraint::Clamp { min, max });
+    }
+
+    /// Wraps the given numeric variable around `range` when updated
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let mut state_machine = AnimationStateMachine::new(
+    ///     "idle",
+    ///     [],
+    ///     vec![],
+    ///     [("angle", StateMachineVariableType::F32(0.))],
+    /// );
+    /// state_machine.declare_variable_wrapped("angle", 0.0..360.);
+    /// state_machine.update_variable("angle", StateMachineVariableType::F32(370.));
+    /// assert!(state_machine.variable("angle").is_some_and(|angle| angle.is_f32(10.)));
+    /// state_machine.update_variable("angle", StateMachineVariableType::F32(-90.));
+    /// assert!(state_machine.variable("angle").is_some_and(|angle| angle.is_f32(270.)));
+    /// ```
+    pub fn declare_variable_wrapped<T: ToString>(&mut self, name: T, range: Range<f32>) {
+        self.declare_constraint(
+            name.to_string(),
+            VariableConstraint::Wrap {
+                start: range.start,
+                end: range.end,
+            },
+        );
+    }
+
+    /// Stores the constraint and applies it to the current value of the variable
+    fn declare_constraint(&mut self, name: String, constraint: VariableConstraint) {
+        if let Some(value) = self.variables.get_mut(&name) {
+            *value = constraint.apply(value.to_owned());
+        }
+        self.mark_dirty(&name);
+        self.constraints.insert(name, constraint);
+    }
+}
+
+/// Evaluation order of the global transitions of an [`AnimationStateMachine`]
+///
+/// See [`AnimationStateMachine::with_global_transitions`]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum GlobalTransitionsOrder {
+    /// Global transitions are evaluated before the per state ones
+    BeforeLocal,
+    /// Global transitions are evaluated after the per state ones
+    #[default]
+    AfterLocal,
+}
+
+/// Incremental builder of an [`AnimationStateMachine`]
+///
+/// Unlike [`AnimationStateMachine::new`], which silently keeps only one of the states
+/// sharing a name, the builder reports duplicate states as an error
+///
+/// Example
+/// ```
+/// # use bevy_simple_state_machine::*;
+/// let result = AnimationStateMachine::builder("idle")
+///     .add_state(AnimationState::empty("idle"))
+///     .add_state(AnimationState::empty("run"))
+///     .add_state(AnimationState::empty("idle"))
+///     .build();
+/// assert_eq!(
+///     result.err(),
+///     Some(StateMachineBuildError::DuplicateState("idle".to_string())),
+/// );
+/// ```
+pub struct AnimationStateMachineBuilder {
+    state_machine: AnimationStateMachine,
+    duplicate_state: Option<String>,
+}
+
+impl AnimationStateMachineBuilder {
+    /// Adds a state, named after its [`AnimationState::name`]
+    pub fn add_state(mut self, state: AnimationState) -> Self {
+        let states = &mut self.state_machine.states;
+        if states.contains_key(&state.name) {
+            self.duplicate_state.get_or_insert(state.name);
+        } else {
+            states.insert(state.name.to_owned(), state);
+        }
+        self
+    }
+
+    /// Adds a transition
+    pub fn add_transition(mut self, transition: StateMachineTransition) -> Self {
+        self.state_machine.transitions.push(transition);
+        self
+    }
+
+    /// Adds a variable with its initial value
+    pub fn add_variable<T: ToString>(mut self, name: T, value: StateMachineVariableType) -> Self {
+        self.state_machine.update_variable(name, value);
+        self
+    }
+
+    /// Builds the [`AnimationStateMachine`]
+    ///
+    /// Fails if two of the added states have the same name
+    pub fn build(self) -> Result<AnimationStateMachine, StateMachineBuildError> {
+        match self.duplicate_state {
+            Some(name) => Err(StateMachineBuildError::DuplicateState(name)),
+            None => {
+                let mut state_machine = self.state_machine;
+                state_machine.initial_variables = state_machine.variables.to_owned();
+                state_machine.index_transitions();
+                Ok(state_machine)
+            }
+        }
+    }
+}
+
+/// Error returned when an [`AnimationStateMachine`] can't be built or merged
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateMachineBuildError {
+    /// Two states have the given name
+    DuplicateState(String),
+}
+
+impl Display for StateMachineBuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DuplicateState(name) => write!(f, "duplicate state name \"{name}\""),
+        }
+    }
+}
+
+impl std::error::Error for StateMachineBuildError {}
+
+/// Error returned when [`AnimationStateMachine::force_transition`] refuses to move the machine
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForceTransitionError {
+    /// The machine has no state with the given name
+    UnknownState(String),
+    /// The machine is already in the requested state
+    SameState,
+    /// The current state is terminal, see [`AnimationState::terminal`]
+    Terminal,
+}
+
+impl Display for ForceTransitionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownState(name) => write!(f, "unknown state \"{name}\""),
+            Self::SameState => write!(f, "the machine is already in the requested state"),
+            Self::Terminal => write!(f, "the current state is terminal"),
+        }
+    }
+}
+
+impl std::error::Error for ForceTransitionError {}
+
+/// Range constraint of a numeric variable
+#[derive(Debug, Clone, Copy, Reflect)]
+enum VariableConstraint {
+    Clamp { min: f32, max: f32 },
+    Wrap { start: f32, end: f32 },
+}
+
+impl VariableConstraint {
+    /// Constrains numeric values, leaving the other ones untouched
+    fn apply(&self, value: StateMachineVariableType) -> StateMachineVariableType {
+        let Some(number) = value.as_f32() else {
+            return value;
+        };
+        let number = match *self {
+            Self::Clamp { min, max } => number.clamp(min, max),
+            Self::Wrap { start, end } if end > start => {
+                start + (number - start).rem_euclid(end - start)
+            }
+            Self::Wrap { .. } => number,
+        };
+        match value {
+            StateMachineVariableType::I32(_) => StateMachineVariableType::I32(number as i32),
+            StateMachineVariableType::U32(_) => StateMachineVariableType::U32(number as u32),
+            _ => StateMachineVariableType::F32(number),
+        }
+    }
+}
+
+/// Blending transition currently in execution
+#[derive(Debug, Clone, Reflect)]
+struct ActiveBlend {
+    elapsed: f32,
+    duration: f32,
+}
+
+impl ActiveBlend {
+    fn progress(&self) -> f32 {
+        if self.duration > 0. {
+            self.elapsed / self.duration
+        } else {
+            1.
+        }
+    }
+}
+
+/// [`AnimationStateMachine`] state structure
+///
+/// Prefer building states with [`AnimationState::new`] and its setters,
+/// so that your code keeps compiling as new fields are added
+#[derive(Debug, Clone, Reflect)]
+pub struct AnimationState {
+    /// Animation clip handle
+    pub clip: Handle<AnimationClip>,
+    /// State name
+    pub name: String,
+    /// If set to `false`, the state is only left once its clip has finished
+    ///
+    /// A clip that is not loaded yet is not finished
+    pub interruptible: bool,
+    /// Playback direction of the animation clip
+    pub playback: PlaybackDirection,
+    /// What the clip does once it reaches its end
+    pub on_finish_behavior: FinishBehavior,
+    /// Effective duration of the clip, in seconds
+    ///
+    /// If set, it replaces the duration of the clip asset when testing if the
+    /// state has finished, which is useful for clips with trailing dead frames
+    pub duration_override: Option<f32>,
+    /// Playback speed of the clip
+    pub speed: f32,
+    /// If set to `true`, entering the state pauses the player on its current pose
+    ///
+    /// The clip of a frozen state is never played, while its transitions are still evaluated.
+    /// Playback resumes once a non frozen state is entered, from the frozen pose if that
+    /// state plays the same clip
+    pub frozen: bool,
+    /// Labelled normalized times at which an [`AnimationMarkerEvent`] is sent
+    ///
+    /// Markers are detected while the clip is played forward, once per loop
+    pub markers: Vec<(f32, String)>,
+    /// Child machine run while the parent machine is in this state
+    ///
+    /// The child plays its own states instead of the clip of this state. The variables of the
+    /// parent are copied into the child each frame, replacing its own ones, so that child
+    /// triggers and [`TriggerContext::delta`] see the parent values. Child machines can be
+    /// nested, and share the time scale of the root machine. Transition and outro clips
+    /// are not played for the transitions of a child.
+    /// The child is reset once the parent leaves this state.
+    /// Since the clip of this state is not played, it should be interruptible
+    #[reflect(ignore)]
+    pub sub_machine: Option<Box<AnimationStateMachine>>,
+    /// Asset path of the clip, loaded through the [`AssetServer`] when the machine is initialized
+    ///
+    /// The path is only used if the state has no [`AnimationState::clip`] yet
+    pub clip_path: Option<String>,
+    /// If set to `true`, no transition is evaluated once the state is entered
+    ///
+    /// Terminal states can only be left with [`AnimationStateMachine::reset`]
+    /// or [`AnimationStateMachine::apply_transition`]
+    pub terminal: bool,
+    /// Name of the variable choosing the clip among [`AnimationState::clip_variants`]
+    pub clip_discriminator: Option<String>,
+    /// Clips played instead of [`AnimationState::clip`] when the discriminator variable
+    /// is equal to the associated value
+    ///
+    /// The variant is chosen when the state is entered, the first matching value wins.
+    /// If no value matches, [`AnimationState::clip`] is played
+    pub clip_variants: Vec<(StateMachineVariableType, Handle<AnimationClip>)>,
+    /// Clip chosen among the [`AnimationState::clip_variants`] on the last entry
+    selected_clip: Option<Handle<AnimationClip>>,
+    /// States of the same sync group share their normalized playback time
+    ///
+    /// When a transition between two states of the same group is taken, the clip of the
+    /// end state starts at the normalized time the clip of the origin state had reached
+    pub sync_group: Option<String>,
+    /// Clip played once when the state is left, before the clip of the next state
+    ///
+    /// It behaves like a [`StateMachineTransition::with_transition_clip`] shared by
+    /// every transition leaving the state, and is played before the transition clip
+    pub outro_clip: Option<Handle<AnimationClip>>,
+    /// Name of the clip in the [`ClipLibrary`], resolved when the machine is initialized
+    ///
+    /// Like [`AnimationState::clip_path`], the name is only used if the state has no
+    /// [`AnimationState::clip`] yet
+    pub library_clip: Option<String>,
+}
+
+impl PartialEq for AnimationState {
+    fn eq(&self, other: &Self) -> bool {
+        self.clip == other.clip
+            && self.name == other.name
+            && self.interruptible == other.interruptible
+            && self.playback == other.playback
+            && self.on_finish_behavior == other.on_finish_behavior
+            && self.duration_override == other.duration_override
+            && self.speed == other.speed
+            && self.frozen == other.frozen
+            && self.markers == other.markers
+            && self.clip_path == other.clip_path
+            && self.terminal == other.terminal
+            && self.clip_discriminator == other.clip_discriminator
+            && self.clip_variants == other.clip_variants
+            && self.sync_group == other.sync_group
+            && self.outro_clip == other.outro_clip
+            && self.library_clip == other.library_clip
+            && match (&self.sub_machine, &other.sub_machine) {
+                (Some(a), Some(b)) => a.structural_eq(b),
+                (a, b) => a.is_none() && b.is_none(),
+            }
+    }
+}
+
+impl Default for AnimationState {
+    fn default() -> Self {
+        Self {
+            clip: Handle::default(),
+            name: String::default(),
+            interruptible: false,
+            playback: PlaybackDirection::default(),
+            on_finish_behavior: FinishBehavior::default(),
+            duration_override: None,
+            speed: 1.,
+            frozen: false,
+            markers: Vec::new(),
+            sub_machine: None,
+            clip_path: None,
+            terminal: false,
+            clip_discriminator: None,
+            clip_variants: Vec::new(),
+            selected_clip: None,
+            sync_group: None,
+            outro_clip: None,
+            library_clip: None,
+        }
+    }
+}
+
+impl AnimationState {
+    /// Creates an interruptible [`AnimationState`] playing the given clip
+    ///
+    /// The other fields are set to their default value, and can be changed with the chainable setters
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::{AnimationState, FinishBehavior, PlaybackDirection};
+    /// # use bevy::prelude::*;
+    /// # let run_clip_handle: Handle<AnimationClip> = Handle::default();
+    /// let run = AnimationState::new("run", run_clip_handle)
+    ///     .interruptible(false)
+    ///     .looping(true)
+    ///     .speed(1.5)
+    ///     .playback(PlaybackDirection::PingPong)
+    ///     .duration_override(0.8);
+    /// assert_eq!(run.name, "run");
+    /// assert!(!run.interruptible);
+    /// assert_eq!(run.on_finish_behavior, FinishBehavior::Loop);
+    /// assert_eq!(run.speed, 1.5);
+    /// assert_eq!(run.playback, PlaybackDirection::PingPong);
+    /// assert_eq!(run.duration_override, Some(0.8));
+    /// ```
+    pub fn new<T: ToString>(name: T, clip: Handle<AnimationClip>) -> Self {
+        Self {
+            clip,
+            name: name.to_string(),
+            interruptible: true,
+            ..Default::default()
+        }
+    }
+
+    /// Sets whether the state can be left before its clip has finished
+    ///
+    /// Non interruptible states wait for their clip to be loaded and played to the end
+    ///
+    /// Example
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_simple_state_machine::AnimationState;
+    /// # let attack_clip: Handle<AnimationClip> = Handle::default();
+    /// let attack = AnimationState::new("attack", attack_clip).interruptible(false);
+    /// assert!(!attack.interruptible);
+    /// ```
+    pub fn interruptible(mut self, interruptible: bool) -> Self {
+        self.interruptible = interruptible;
+        self
+    }
+
+    /// Sets whether the clip loops, or holds its last frame once finished
+    pub fn looping(mut self, looping: bool) -> Self {
+        self.on_finish_behavior = match looping {
+            true => FinishBehavior::Loop,
+            false => FinishBehavior::Hold,
+        };
+        self
+    }
+
+    /// Sets the playback speed of the clip
+    pub fn speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Sets the direction in which the clip is played
+    pub fn playback(mut self, playback: PlaybackDirection) -> Self {
+        self.playback = playback;
+        self
+    }
+
+    /// Sets the behavior of the state once its clip reaches its end
+    pub fn on_finish_behavior(mut self, on_finish_behavior: FinishBehavior) -> Self {
+        self.on_finish_behavior = on_finish_behavior;
+        self
+    }
+
+    /// Sets the duration, in seconds, used instead of the duration of the clip,
+    /// see [`AnimationState::duration_override`]
+    pub fn duration_override(mut self, duration: f32) -> Self {
+        self.duration_override = Some(duration);
+        self
+    }
+
+    /// Sets the asset path of the clip, see [`AnimationState::clip_path`]
+    pub fn clip_path(mut self, clip_path: impl ToString) -> Self {
+        self.clip_path = Some(clip_path.to_string());
+        self
+    }
+
+    /// Sets the name of the clip in the [`ClipLibrary`], see [`AnimationState::library_clip`]
+    pub fn library_clip(mut self, clip_name: impl ToString) -> Self {
+        self.library_clip = Some(clip_name.to_string());
+        self
+    }
+
+    /// Sets whether entering the state freezes the player on its current pose
+    ///
+    /// Example
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_simple_state_machine::*;
+    /// let stunned = AnimationState::new("stunned", Handle::weak_from_u128(2)).frozen(true);
+    /// ```
+    pub fn frozen(mut self, frozen: bool) -> Self {
+        self.frozen = frozen;
+        self
+    }
+
+    /// Creates an interruptible [`AnimationState`] whose clip is loaded from the given asset path
+    ///
+    /// See [`AnimationState::clip_path`]
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::AnimationState;
+    /// let walk = AnimationState::from_path("walk", "character.glb#Animation0");
+    /// assert_eq!(walk.clip_path.as_deref(), Some("character.glb#Animation0"));
+    /// ```
+    pub fn from_path<T: ToString>(name: T, clip_path: impl ToString) -> Self {
+        Self::new(name, Handle::default()).clip_path(clip_path)
+    }
+
+    /// Creates an interruptible [`AnimationState`] whose clip is taken from the [`ClipLibrary`]
+    ///
+    /// See [`AnimationState::library_clip`]
+    ///
+    /// Example
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_simple_state_machine::*;
+    /// # let walk_clip: Handle<AnimationClip> = Handle::default();
+    /// let mut library = ClipLibrary::default();
+    /// library.insert("walk", walk_clip);
+    /// let walk = AnimationState::from_library("walk", "walk");
+    /// App::new()
+    ///     .add_plugins(DefaultPlugins)
+    ///     .add_plugins(SimpleStateMachinePlugin::new())
+    ///     .insert_resource(library);
+    /// ```
+    pub fn from_library<T: ToString>(name: T, clip_name: impl ToString) -> Self {
+        Self::new(name, Handle::default()).library_clip(clip_name)
+    }
+
+    /// Sets the child machine run while the parent machine is in this state
+    ///
+    /// The child sees the variables of the parent, plays its own clips on the player of the
+    /// parent, and keeps running while the parent transitions are blocked, such as in
+    /// terminal or non interruptible hosts. Only [`AnimationStateMachine::set_lock_transitions`]
+    /// also halts the child
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::prelude::*;
+    /// let combat = AnimationStateMachine::from_states(
+    ///     "guard",
+    ///     ["guard", "strike"].map(AnimationState::empty),
+    ///     vec![StateMachineTransition::always("guard", "strike")],
+    ///     [],
+    /// );
+    /// let combat = AnimationState::empty("combat").sub_machine(combat);
+    /// ```
+    pub fn sub_machine(mut self, sub_machine: AnimationStateMachine) -> Self {
+        self.sub_machine = Some(Box::new(sub_machine));
+        self
+    }
+
+    /// Sets whether the state is terminal, see [`AnimationState::terminal`]
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let mut state_machine = AnimationStateMachine::new(
+    ///     "dead",
+    ///     [
+    ///         ("idle", AnimationState::empty("idle")),
+    ///         ("dead", AnimationState::empty("dead").terminal(true)),
+    ///     ],
+    ///     vec![StateMachineTransition::from_any("idle", StateMachineTrigger::Always)],
+    ///     [],
+    /// );
+    /// assert_eq!(state_machine.evaluate_transition(), None);
+    /// ```
+    pub fn terminal(mut self, terminal: bool) -> Self {
+        self.terminal = terminal;
+        self
+    }
+
+    /// Sets the variable choosing the clip variant, see [`AnimationState::clip_variants`]
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::prelude::*;
+    /// # use bevy::prelude::*;
+    /// let armed = Handle::weak_from_u128(1);
+    /// let unarmed = Handle::weak_from_u128(2);
+    /// let mut state_machine = AnimationStateMachine::new(
+    ///     "walk",
+    ///     [
+    ///         ("walk", AnimationState::empty("walk")),
+    ///         (
+    ///             "idle",
+    ///             AnimationState::new("idle", unarmed.clone())
+    ///                 .clip_discriminator("armed")
+    ///                 .clip_variant(true, armed.clone()),
+    ///         ),
+    ///     ],
+    ///     vec![],
+    ///     [("armed", false.into())],
+    /// );
+    /// state_machine.update_variable("armed", true.into());
+    /// state_machine.apply_transition(&"idle".into());
+    /// assert_eq!(state_machine.state("idle").unwrap().selected_clip(), Some(&armed));
+    /// state_machine.apply_transition(&"walk".into());
+    /// state_machine.update_variable("armed", false.into());
+    /// state_machine.apply_transition(&"idle".into());
+    /// assert_eq!(state_machine.state("idle").unwrap().selected_clip(), None);
+    /// ```
+    pub fn clip_discriminator<T: ToString>(mut self, variable: T) -> Self {
+        self.clip_discriminator = Some(variable.to_string());
+        self
+    }
+
+    /// Adds a clip played when the discriminator variable is equal to `value`,
+    /// see [`AnimationState::clip_variants`]
+    pub fn clip_variant(
+        mut self,
+        value: impl Into<StateMachineVariableType>,
+        clip: Handle<AnimationClip>,
+    ) -> Self {
+        self.clip_variants.push((value.into(), clip));
+        self
+    }
+
+    /// Sets the sync group of the state, see [`AnimationState::sync_group`]
+    ///
+    /// Example
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_simple_state_machine::AnimationState;
+    /// # let (walk_clip, run_clip): (Handle<AnimationClip>, Handle<AnimationClip>) = Default::default();
+    /// let walk = AnimationState::new("walk", walk_clip).sync_group("locomotion");
+    /// let run = AnimationState::new("run", run_clip).sync_group("locomotion");
+    /// ```
+    pub fn sync_group<T: ToString>(mut self, group: T) -> Self {
+        self.sync_group = Some(group.to_string());
+        self
+    }
+
+    /// Sets the clip played when leaving the state, see [`AnimationState::outro_clip`]
+    ///
+    /// Example
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_simple_state_machine::AnimationState;
+    /// # let (aim_clip, holster_clip): (Handle<AnimationClip>, Handle<AnimationClip>) = Default::default();
+    /// let aim = AnimationState::new("aim", aim_clip).outro_clip(holster_clip);
+    /// ```
+    pub fn outro_clip(mut self, clip: Handle<AnimationClip>) -> Self {
+        self.outro_clip = Some(clip);
+        self
+    }
+
+    /// Adds a marker at the given normalized time, see [`AnimationState::markers`]
+    pub fn marker<T: ToString>(mut self, time: f32, label: T) -> Self {
+        self.markers.push((time, label.to_string()));
+        self
+    }
+
+    /// Creates an [`AnimationState`] without an animation clip
+    ///
+    /// Entering an empty state pauses the [`AnimationPlayer`] instead of playing a clip.
+    /// This is useful for explicit "do nothing" states
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::AnimationState;
+    /// let stopped = AnimationState::empty("stopped");
+    /// assert!(stopped.is_empty());
+    /// assert!(stopped.interruptible);
+    /// ```
+    pub fn empty<T: ToString>(name: T) -> Self {
+        Self {
+            clip: Handle::default(),
+            name: name.to_string(),
+            interruptible: true,
+            ..Default::default()
         }
     }
 
-    fn transitions_from_state(&self, state_name: &String) -> Vec<StateMachineTransition> {
-        self.transitions
-            .iter()
-            .filter(|t| {
-                t.start_state == AnimationStateRef::StateName(state_name.to_owned())
-                    || t.start_state.is_any()
-            })
-            .map(|t| t.to_owned())
-            .collect()
+    /// Tests if the state has no animation clip
+    pub fn is_empty(&self) -> bool {
+        *self.active_clip() == Handle::default()
     }
 
-    fn transitions_from_current_state(&self) -> Vec<StateMachineTransition> {
-        self.transitions_from_state(&self.current_state)
+    /// Clip chosen among the [`AnimationState::clip_variants`] when the state was last entered
+    ///
+    /// Returns `None` if no variant matched, in which case [`AnimationState::clip`] is played
+    pub fn selected_clip(&self) -> Option<&Handle<AnimationClip>> {
+        self.selected_clip.as_ref()
     }
 
-    /// Updates the value of the given variable
-    pub fn update_variable<T: ToString>(&mut self, name: T, value: StateMachineVariableType) {
-        self.variables.insert(name.to_string(), value);
+    /// Clip currently played by the state, either the selected variant or [`AnimationState::clip`]
+    fn active_clip(&self) -> &Handle<AnimationClip> {
+        self.selected_clip.as_ref().unwrap_or(&self.clip)
     }
-}
 
-/// [`AnimationStateMachine`] state structure
-#[derive(Default, Debug, Clone, Reflect)]
-pub struct AnimationState {
-    /// Animation clip handle
-    pub clip: Handle<AnimationClip>,
-    /// State name
-    pub name: String,
-    /// If set to `true`, the animation will only be interrupted once any valid transition is triggered
-    pub interruptible: bool,
-}
+    /// Chooses the clip variant matching the discriminator variable,
+    /// in this state and in the current state of its sub machine
+    fn select_clip(&mut self, variables: &StateMachineVariables) {
+        self.selected_clip = self
+            .clip_discriminator
+            .as_ref()
+            .and_then(|name| variables.get(name))
+            .and_then(|value| self.clip_variants.iter().find(|(v, _)| v == value))
+            .map(|(_, clip)| clip.to_owned());
+        if let Some(sub_machine) = &mut self.sub_machine {
+            if let Some(state) = sub_machine.states.get_mut(&sub_machine.current_state) {
+                state.select_clip(variables);
+            }
+        }
+    }
 
-impl AnimationState {
-    fn state_ref(&self) -> AnimationStateRef {
+    /// Effective duration of the state, in seconds
+    ///
+    /// This is the [`AnimationState::duration_override`] if set, otherwise the duration
+    /// of the clip. Returns `None` if there is no override and the clip is not loaded
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::AnimationState;
+    /// # use bevy::prelude::*;
+    /// let animations = Assets::<AnimationClip>::default();
+    /// let attack = AnimationState::empty("attack");
+    /// assert_eq!(attack.duration(&animations), None);
+    /// let attack = attack.duration_override(0.8);
+    /// assert_eq!(attack.duration(&animations), Some(0.8));
+    /// ```
+    pub fn duration(&self, animations: &Assets<AnimationClip>) -> Option<f32> {
+        self.duration_override.or_else(|| {
+            animations
+                .get(self.active_clip())
+                .map(|clip| clip.duration())
+        })
+    }
+
+    /// Creates a [`AnimationStateRef`] to the state
+    pub fn state_ref(&self) -> AnimationStateRef {
         AnimationStateRef::StateName(self.name.to_owned())
     }
+
+    /// Starts the playback of the state on the given player
+    fn play(
+        &self,
+        player: &mut AnimationPlayer,
+        animations: &Assets<AnimationClip>,
+        transition_duration: Option<Duration>,
+    ) {
+        if let Some(sub_machine) = &self.sub_machine {
+            if let Some(state) = sub_machine.current_state() {
+                state.play(player, animations, transition_duration);
+            }
+            return;
+        }
+        if self.is_empty() || self.frozen {
+            player.pause();
+            return;
+        }
+        if let Some(transition_duration) = transition_duration {
+            player.play_with_transition(self.active_clip().to_owned(), transition_duration);
+        } else {
+            player.play(self.active_clip().to_owned());
+        }
+        self.apply_playback(player);
+        if let (PlaybackDirection::Reverse, Some(clip)) =
+            (self.playback, animations.get(self.active_clip()))
+        {
+            player.seek_to(reverse_start(clip.duration()));
+        }
+    }
+
+    /// Like [`AnimationState::play`], but resumes the clip paused by a frozen `origin`
+    /// state instead of restarting it, if the state plays the same clip
+    fn play_from(
+        &self,
+        origin: &AnimationState,
+        player: &mut AnimationPlayer,
+        animations: &Assets<AnimationClip>,
+        transition_duration: Option<Duration>,
+    ) {
+        if origin.frozen
+            && self.sub_machine.is_none()
+            && !self.is_empty()
+            && !self.frozen
+            && player.animation_clip() == self.active_clip()
+        {
+            self.apply_playback(player);
+            return;
+        }
+        self.play(player, animations, transition_duration);
+    }
+
+    /// Applies the speed, [`FinishBehavior`] and [`PlaybackDirection`] of the state
+    /// to the clip being played, and resumes it
+    fn apply_playback(&self, player: &mut AnimationPlayer) {
+        player.set_speed(self.speed);
+        player.resume();
+        match self.on_finish_behavior {
+            FinishBehavior::Loop => player.repeat(),
+            FinishBehavior::Hold | FinishBehavior::Stop => {
+                player.set_repeat(RepeatAnimation::Never)
+            }
+        };
+        match self.playback {
+            PlaybackDirection::Forward => {}
+            PlaybackDirection::Reverse => {
+                player.set_speed(-player.speed().abs());
+            }
+            PlaybackDirection::PingPong => {
+                player.repeat();
+            }
+        }
+    }
+
+    /// Enforces the [`FinishBehavior`] of the state and flips the playback direction
+    /// of [`PlaybackDirection::PingPong`] states each time the clip reaches one of its ends
+    fn update_playback(&self, player: &mut AnimationPlayer, animations: &Assets<AnimationClip>) {
+        if let Some(sub_machine) = &self.sub_machine {
+            if let Some(state) = sub_machine.states.get(&sub_machine.current_state) {
+                state.update_playback(player, animations);
+            }
+            return;
+        }
+        if self.is_empty() || self.frozen {
+            return;
+        }
+        if player.is_finished() {
+            if let Some(clip) = animations.get(self.active_clip()) {
+                let (start, end) = match player.is_playback_reversed() {
+                    true => (clip.duration(), 0.),
+                    false => (0., clip.duration()),
+                };
+                match self.on_finish_behavior {
+                    FinishBehavior::Hold => player.seek_to(end).pause(),
+                    FinishBehavior::Stop => player.seek_to(start).pause(),
+                    FinishBehavior::Loop => {}
+                }
+            }
+        }
+        if self.playback != PlaybackDirection::PingPong {
+            return;
+        }
+        let forward = player.completions() & 1 == 0;
+        if forward == player.is_playback_reversed() {
+            player.set_speed(-player.speed());
+            if let Some(clip) = animations.get(self.active_clip()) {
+                player.seek_to(if forward {
+                    0.
+                } else {
+                    reverse_start(clip.duration())
+                });
+            }
+        }
+    }
+}
+
+/// Playback direction of an [`AnimationState`]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum PlaybackDirection {
+    /// The clip is played from start to end
+    #[default]
+    Forward,
+    /// The clip is played from end to start
+    ///
+    /// Example
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_simple_state_machine::{AnimationState, PlaybackDirection};
+    /// # let rewind_clip: Handle<AnimationClip> = Handle::default();
+    /// let rewind = AnimationState::new("rewind", rewind_clip).playback(PlaybackDirection::Reverse);
+    /// ```
+    Reverse,
+    /// The clip is played forward and backward in a loop
+    ///
+    /// ## Note
+    /// Ping-pong clips never finish, regardless of the state [`FinishBehavior`],
+    /// so non interruptible states using it will never be left
+    PingPong,
+}
+
+/// Behavior of an [`AnimationState`] once its clip reaches its end
+///
+/// Example
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_simple_state_machine::{AnimationState, FinishBehavior};
+/// # let wave_clip: Handle<AnimationClip> = Handle::default();
+/// let wave = AnimationState::new("wave", wave_clip).on_finish_behavior(FinishBehavior::Stop);
+/// ```
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum FinishBehavior {
+    /// The player is paused on the last frame of the clip
+    #[default]
+    Hold,
+    /// The clip restarts from the beginning
+    ///
+    /// ## Note
+    /// Looping clips never finish, so non interruptible states using it
+    /// will never be left
+    Loop,
+    /// The player is paused on the first frame of the clip
+    Stop,
 }
 
 /// Reference to an [`AnimationState`] name
@@ -407,6 +4279,48 @@ impl AnimationStateRef {
     }
 }
 
+impl From<&AnimationState> for AnimationStateRef {
+    /// Creates a [`AnimationStateRef::StateName`] referencing the given state
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::{AnimationState, AnimationStateRef};
+    /// let idle = AnimationState::empty("idle");
+    /// assert_eq!(AnimationStateRef::from(&idle), AnimationStateRef::StateName("idle".to_string()));
+    /// ```
+    fn from(state: &AnimationState) -> Self {
+        state.state_ref()
+    }
+}
+
+impl From<&str> for AnimationStateRef {
+    /// Creates a [`AnimationStateRef::StateName`]
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::AnimationStateRef;
+    /// let state_ref: AnimationStateRef = "idle".into();
+    /// assert_eq!(state_ref, AnimationStateRef::StateName("idle".to_string()));
+    /// ```
+    fn from(name: &str) -> Self {
+        Self::from_string(name)
+    }
+}
+
+impl From<String> for AnimationStateRef {
+    /// Creates a [`AnimationStateRef::StateName`]
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::AnimationStateRef;
+    /// let state_ref = AnimationStateRef::from("run".to_string());
+    /// assert_eq!(state_ref, AnimationStateRef::StateName("run".to_string()));
+    /// ```
+    fn from(name: String) -> Self {
+        Self::StateName(name)
+    }
+}
+
 impl Display for AnimationStateRef {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -425,16 +4339,15 @@ impl Display for AnimationStateRef {
 /// let immediate_transition = StateMachineTransition::immediate(
 ///     AnimationStateRef::from_string("idle"),
 ///     AnimationStateRef::from_string("run"),
-///     StateMachineTrigger::from(|vars| vars["run"].is_bool(true)),
+///     StateMachineTrigger::from(|vars| vars.get("run").is_some_and(|run| run.is_bool(true))),
 /// );
 ///
 /// let blending_transition = StateMachineTransition::blend(
 ///     AnimationStateRef::from_string("idle"),
 ///     AnimationStateRef::from_string("run"),
-///     StateMachineTrigger::from(|vars| vars["run"].is_bool(true)),
+///     StateMachineTrigger::from(|vars| vars.get("run").is_some_and(|run| run.is_bool(true))),
 ///     Duration::from_secs(10),
 /// );
-
 /// ```
 #[derive(Clone, Reflect)]
 pub struct StateMachineTransition {
@@ -450,9 +4363,94 @@ pub struct StateMachineTransition {
     pub trigger: StateMachineTrigger,
     /// Tranisition Duration
     pub transition_duration: Option<Duration>,
+    /// Gate variable for the transition
+    ///
+    /// If set, the transition is only considered while the named variable
+    /// holds the given value, regardless of its trigger
+    pub enabled_while: Option<(String, StateMachineVariableType)>,
+    /// Side effect applied to the state machine variables when the transition is taken
+    ///
+    /// A single transition is taken per frame, and its side effect runs once every trigger
+    /// of the frame has been evaluated, so all triggers see the same variables
+    #[reflect(ignore)]
+    pub on_take: Option<TransitionSideEffect>,
+    /// Priority of the transition over the other matching ones
+    ///
+    /// See [`MultiMatchPolicy::HighestPriority`]
+    pub priority: i32,
+    /// Disabled transitions are never taken, see [`AnimationStateMachine::set_transition_enabled`]
+    pub enabled: bool,
+    /// Clip played before the clip of the end state, see [`StateMachineTransition::with_transition_clip`]
+    pub transition_clip: Option<Handle<AnimationClip>>,
+    /// Free form label copied into the [`TransitionEndedEvent`] of the transition
+    ///
+    /// This lets feedback systems map transitions to sounds or effects
+    /// without knowing the structure of the machine
+    pub tag: Option<String>,
 }
 
+/// Side effect of a [`StateMachineTransition`], see [`StateMachineTransition::with_on_take`]
+pub type TransitionSideEffect = Arc<dyn Fn(&mut StateMachineVariables) + Send + Sync>;
+
 impl StateMachineTransition {
+    /// Creates a new [`StateMachineTransition`] without a transition duration between the named states
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let transition = StateMachineTransition::new("idle", "run", StateMachineTrigger::Always);
+    /// assert_eq!(transition.start_state, AnimationStateRef::from_string("idle"));
+    /// assert_eq!(transition.end_state, AnimationStateRef::from_string("run"));
+    /// assert_eq!(transition.transition_duration, None);
+    /// ```
+    pub fn new(from: impl ToString, to: impl ToString, trigger: StateMachineTrigger) -> Self {
+        Self::immediate(
+            AnimationStateRef::from_string(from),
+            AnimationStateRef::from_string(to),
+            trigger,
+        )
+    }
+
+    /// Creates a new [`StateMachineTransition`] between the named states with a
+    /// [`StateMachineTrigger::Always`] trigger
+    pub fn always(from: impl ToString, to: impl ToString) -> Self {
+        Self::new(from, to, StateMachineTrigger::Always)
+    }
+
+    /// Creates a new [`StateMachineTransition`] between the named states with a
+    /// [`StateMachineTrigger::Never`] trigger
+    pub fn never(from: impl ToString, to: impl ToString) -> Self {
+        Self::new(from, to, StateMachineTrigger::Never)
+    }
+
+    /// Creates a new [`StateMachineTransition`] between the named states, taken when the
+    /// given [`StateMachineVariableType::Trigger`] is set
+    ///
+    /// Taking the transition consumes the trigger, resetting it
+    pub fn on_trigger<T: ToString>(from: impl ToString, to: impl ToString, trigger: T) -> Self {
+        let trigger = trigger.to_string();
+        Self::new(from, to, StateMachineTrigger::triggered(&trigger)).with_on_take(move |vars| {
+            vars.insert(trigger.to_owned(), StateMachineVariableType::Trigger(false));
+        })
+    }
+
+    /// Creates a new [`StateMachineTransition`] from [`AnimationStateRef::AnyState`] to the named state
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let transition = StateMachineTransition::from_any("dead", StateMachineTrigger::Always);
+    /// assert!(transition.start_state.is_any());
+    /// assert_eq!(transition.end_state, AnimationStateRef::from_string("dead"));
+    /// ```
+    pub fn from_any(to: impl ToString, trigger: StateMachineTrigger) -> Self {
+        Self::immediate(
+            AnimationStateRef::AnyState,
+            AnimationStateRef::from_string(to),
+            trigger,
+        )
+    }
+
     /// Creates a new [`StateMachineTransition`] without a transition duration
     pub fn immediate(
         start_state: AnimationStateRef,
@@ -464,21 +4462,206 @@ impl StateMachineTransition {
             end_state,
             trigger,
             transition_duration: None,
+            enabled_while: None,
+            on_take: None,
+            priority: 0,
+            enabled: true,
+            transition_clip: None,
+            tag: None,
+        }
+    }
+
+    /// Creates a new [`StateMachineTransition`] with the given transition duration
+    pub fn blend(
+        start_state: AnimationStateRef,
+        end_state: AnimationStateRef,
+        trigger: StateMachineTrigger,
+        transition_duration: Duration,
+    ) -> Self {
+        Self {
+            start_state,
+            end_state,
+            trigger,
+            transition_duration: Some(transition_duration),
+            enabled_while: None,
+            on_take: None,
+            priority: 0,
+            enabled: true,
+            transition_clip: None,
+            tag: None,
+        }
+    }
+
+    /// Makes the transition active only while the given variable holds the given value
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// // "attack" can't be reached while the character is stunned
+    /// let transition = StateMachineTransition::always("idle", "attack")
+    ///     .with_enabled_while("stunned", StateMachineVariableType::Bool(false));
+    /// let mut state_machine = AnimationStateMachine::from_states(
+    ///     "idle",
+    ///     ["idle", "attack"].map(AnimationState::empty),
+    ///     vec![transition],
+    ///     [("stunned", true.into())],
+    /// );
+    /// assert_eq!(state_machine.evaluate_transition(), None);
+    /// state_machine.update_variable("stunned", false.into());
+    /// assert_eq!(state_machine.evaluate_transition(), Some("attack".into()));
+    /// ```
+    pub fn with_enabled_while<T: ToString>(
+        mut self,
+        variable: T,
+        value: StateMachineVariableType,
+    ) -> Self {
+        self.enabled_while = Some((variable.to_string(), value));
+        self
+    }
+
+    /// Sets a side effect applied to the state machine variables when the transition is taken
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let transition = StateMachineTransition::always("idle", "jump").with_on_take(|vars| {
+    ///     let Some(StateMachineVariableType::I32(jumps)) = vars.get_mut("jumps") else {
+    ///         return;
+    ///     };
+    ///     *jumps += 1;
+    /// });
+    /// ```
+    pub fn with_on_take(
+        mut self,
+        f: impl Fn(&mut StateMachineVariables) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_take = Some(Arc::new(f));
+        self
+    }
+
+    /// Sets the priority of the transition
+    ///
+    /// When several transitions match on the same frame, the one with the highest priority is taken
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let state_machine = AnimationStateMachine::new(
+    ///     "idle",
+    ///     [
+    ///         ("idle", AnimationState::empty("idle")),
+    ///         ("walk", AnimationState::empty("walk")),
+    ///         ("hurt", AnimationState::empty("hurt")),
+    ///     ],
+    ///     vec![
+    ///         StateMachineTransition::always("idle", "walk"),
+    ///         StateMachineTransition::always("idle", "hurt").with_priority(10),
+    ///     ],
+    ///     [],
+    /// );
+    /// assert_eq!(
+    ///     state_machine.preview_transition(&StateMachineVariables::default()),
+    ///     Some("hurt".into()),
+    /// );
+    /// ```
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Plays the given clip once when the transition is taken, before the clip of the end state
+    ///
+    /// The machine enters the end state immediately, but no transition is evaluated
+    /// until the transition clip has finished, waiting for the clip to load if needed.
+    /// The [`AnimationState::outro_clip`] of the origin state, if any, is played first.
+    /// Additional players keep playing the origin state meanwhile.
+    /// Transition clips are ignored in sub machines
+    ///
+    /// Example
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_simple_state_machine::*;
+    /// # let turn_clip: Handle<AnimationClip> = Handle::default();
+    /// let turn_around = StateMachineTransition::always("idle", "run").with_transition_clip(turn_clip);
+    /// assert!(turn_around.transition_clip.is_some());
+    /// ```
+    pub fn with_transition_clip(mut self, clip: Handle<AnimationClip>) -> Self {
+        self.transition_clip = Some(clip);
+        self
+    }
+
+    /// Sets the tag of the transition, see [`StateMachineTransition::tag`]
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let jump = StateMachineTransition::always("idle", "jump").with_tag("whoosh");
+    /// assert_eq!(jump.tag.as_deref(), Some("whoosh"));
+    /// ```
+    pub fn with_tag<T: ToString>(mut self, tag: T) -> Self {
+        self.tag = Some(tag.to_string());
+        self
+    }
+
+    /// Compares two transitions, ignoring the trigger functions
+    fn structural_eq(&self, other: &Self) -> bool {
+        self.start_state == other.start_state
+            && self.end_state == other.end_state
+            && self.transition_duration == other.transition_duration
+            && self.enabled_while == other.enabled_while
+            && self.on_take.is_some() == other.on_take.is_some()
+            && self.priority == other.priority
+            && self.enabled == other.enabled
+            && self.transition_clip == other.transition_clip
+            && self.tag == other.tag
+            && self.trigger.structural_eq(&other.trigger)
+    }
+
+    /// Tests if the transition is enabled and its gate, if any, is currently open
+    fn is_enabled(&self, variables: &StateMachineVariables) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        match &self.enabled_while {
+            Some((name, value)) => variables.get(name) == Some(value),
+            None => true,
         }
     }
 
-    /// Creates a new [`StateMachineTransition`] with the given transition duration
-    pub fn blend(
-        start_state: AnimationStateRef,
-        end_state: AnimationStateRef,
-        trigger: StateMachineTrigger,
-        transition_duration: Duration,
-    ) -> Self {
-        Self {
-            start_state,
-            end_state,
-            trigger,
-            transition_duration: Some(transition_duration),
+    /// Names of the variables read by the trigger and the gate of the transition
+    ///
+    /// Returns `None` if the trigger reads unknown inputs, which is the case of closures
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let fast = StateMachineTrigger::from_comparison(Comparison::new("speed", ComparisonOp::Gt, 3.));
+    /// let transition = StateMachineTransition::new("walk", "run", fast)
+    ///     .with_enabled_while("grounded", StateMachineVariableType::Bool(true));
+    /// assert_eq!(transition.inputs(), Some(vec!["speed", "grounded"]));
+    /// assert_eq!(StateMachineTransition::new("walk", "run", StateMachineTrigger::from(|_| true)).inputs(), None);
+    /// ```
+    pub fn inputs(&self) -> Option<Vec<&str>> {
+        let StateMachineTrigger::Comparison(comparison) = &self.trigger else {
+            return None;
+        };
+        Some(
+            std::iter::once(comparison.variable.as_str())
+                .chain(self.enabled_while.as_ref().map(|(name, _)| name.as_str()))
+                .collect(),
+        )
+    }
+
+    /// Evaluates the trigger of the transition
+    ///
+    /// A panicking trigger is logged and treated as not firing
+    fn fires(&self, context: &TriggerContext) -> bool {
+        match panic::catch_unwind(AssertUnwindSafe(|| self.trigger.evaluate(context))) {
+            Ok(fires) => fires,
+            Err(_) => {
+                warn!("trigger of {} panicked, treating it as not firing", self);
+                false
+            }
         }
     }
 }
@@ -498,13 +4681,17 @@ impl Display for StateMachineTransition {
 /// Current values are:
 ///  - Never: the transition is never executed
 ///  - Always: the transition is always executed. This happens on the next frame or once the previous animation has concluded
+///  - AlwaysAfter: the transition is always executed, once the machine has been in the current state for the given duration
 ///  - Condition: supports a custom condition of type `Fn(&StateMachineVariables) -> bool + Send + Sync`
+///  - Context: supports a custom condition of type `Fn(&TriggerContext) -> bool + Send + Sync`
+///  - Comparison: compares a variable with a value, see [`Comparison`]
+///  - OnBlendComplete: the transition is executed on the frame after the blend into the current state completes
 ///
 /// Example
 /// ```
 /// # use bevy_simple_state_machine::StateMachineTrigger;
 /// // this trigger returns true if the state machine variable "run" is set to true
-/// let trigger = StateMachineTrigger::from(|vars| vars["run"].is_bool(true));
+/// let trigger = StateMachineTrigger::from(|vars| vars.get("run").is_some_and(|run| run.is_bool(true)));
 /// ```
 #[derive(Default, Clone)]
 pub enum StateMachineTrigger {
@@ -513,33 +4700,564 @@ pub enum StateMachineTrigger {
     Never,
     /// The transition is always executed. This happens on the next frame or once the previous animation has concluded
     Always,
+    /// The transition is always executed, once the machine has spent the given time in the current state
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::prelude::*;
+    /// # use std::time::Duration;
+    /// // leave the intro half a second after entering it
+    /// let trigger = StateMachineTrigger::AlwaysAfter(Duration::from_millis(500));
+    /// let transition = StateMachineTransition::new("intro", "idle", trigger);
+    /// ```
+    AlwaysAfter(Duration),
     /// The transition is executed once the given function evaluates to `true`
     Condition(Arc<dyn Fn(&StateMachineVariables) -> bool + Send + Sync>),
+    /// The transition is executed once the given function evaluates to `true`
+    ///
+    /// Unlike [`StateMachineTrigger::Condition`], the function has access to
+    /// the full [`TriggerContext`]
+    Context(Arc<dyn Fn(&TriggerContext) -> bool + Send + Sync>),
+    /// The transition is executed once the given [`Comparison`] evaluates to `true`
+    ///
+    /// Unlike closures, the variable read by the comparison is known to the machine,
+    /// see [`AnimationStateMachine::set_lazy_evaluation`]
+    Comparison(Comparison),
+    /// The transition is executed on the first frame after the blend into the current state completed
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::prelude::*;
+    /// // start running once the blend into walking completed
+    /// let transition = StateMachineTransition::new("walk", "run", StateMachineTrigger::OnBlendComplete);
+    /// ```
+    OnBlendComplete,
+}
+
+/// Reflectable kind of a [`StateMachineTrigger`], without its condition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum TriggerKind {
+    /// See [`StateMachineTrigger::Never`]
+    Never,
+    /// See [`StateMachineTrigger::Always`]
+    Always,
+    /// See [`StateMachineTrigger::AlwaysAfter`]
+    AlwaysAfter(Duration),
+    /// See [`StateMachineTrigger::Condition`]
+    Condition,
+    /// See [`StateMachineTrigger::Context`]
+    Context,
+    /// See [`StateMachineTrigger::Comparison`]
+    Comparison,
+    /// See [`StateMachineTrigger::OnBlendComplete`]
+    OnBlendComplete,
+}
+
+/// Data driven comparison of a variable with a value, see [`StateMachineTrigger::from_comparison`]
+///
+/// Comparisons of missing variables, or of variables of a different type than the value,
+/// are always `false`. Ordering operators are `false` for non numeric values
+///
+/// Example
+/// ```
+/// # use bevy_simple_state_machine::*;
+/// let variables = state_machine_variables([("speed", 2.0.into()), ("ammo", 3.into())]);
+/// let speed = |op| Comparison::new("speed", op, 2.).evaluate(&variables);
+/// assert!(speed(ComparisonOp::Eq) && !speed(ComparisonOp::Ne));
+/// assert!(!speed(ComparisonOp::Lt) && speed(ComparisonOp::Le));
+/// assert!(!speed(ComparisonOp::Gt) && speed(ComparisonOp::Ge));
+/// let ammo = |op| Comparison::new("ammo", op, 5).evaluate(&variables);
+/// assert!(!ammo(ComparisonOp::Eq) && ammo(ComparisonOp::Ne));
+/// assert!(ammo(ComparisonOp::Lt) && ammo(ComparisonOp::Le));
+/// assert!(!ammo(ComparisonOp::Gt) && !ammo(ComparisonOp::Ge));
+/// // mismatched types and missing variables never match
+/// assert!(!Comparison::new("ammo", ComparisonOp::Ne, 5.).evaluate(&variables));
+/// assert!(!Comparison::new("health", ComparisonOp::Ne, 5).evaluate(&variables));
+/// ```
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub struct Comparison {
+    /// Name of the compared variable
+    pub variable: String,
+    /// Comparison operator, with the variable on its left side
+    pub op: ComparisonOp,
+    /// Value the variable is compared to
+    pub value: StateMachineVariableType,
+}
+
+/// Operator of a [`Comparison`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum ComparisonOp {
+    /// Equal to
+    Eq,
+    /// Not equal to
+    Ne,
+    /// Less than
+    Lt,
+    /// Less than or equal to
+    Le,
+    /// Greater than
+    Gt,
+    /// Greater than or equal to
+    Ge,
+}
+
+impl Comparison {
+    /// Creates a new [`Comparison`] of the named variable with the given value
+    pub fn new<T: ToString>(
+        variable: T,
+        op: ComparisonOp,
+        value: impl Into<StateMachineVariableType>,
+    ) -> Self {
+        Self {
+            variable: variable.to_string(),
+            op,
+            value: value.into(),
+        }
+    }
+
+    /// Evaluates the comparison against the given variables
+    pub fn evaluate(&self, variables: &StateMachineVariables) -> bool {
+        let Some(variable) = variables.get(&self.variable) else {
+            return false;
+        };
+        if std::mem::discriminant(variable) != std::mem::discriminant(&self.value) {
+            return false;
+        }
+        let ordering = match (variable, &self.value) {
+            (StateMachineVariableType::F32(a), StateMachineVariableType::F32(b)) => {
+                a.partial_cmp(b)
+            }
+            (StateMachineVariableType::I32(a), StateMachineVariableType::I32(b)) => Some(a.cmp(b)),
+            (StateMachineVariableType::U32(a), StateMachineVariableType::U32(b)) => Some(a.cmp(b)),
+            _ => None,
+        };
+        match self.op {
+            ComparisonOp::Eq => *variable == self.value,
+            ComparisonOp::Ne => *variable != self.value,
+            ComparisonOp::Lt => ordering.is_some_and(Ordering::is_lt),
+            ComparisonOp::Le => ordering.is_some_and(Ordering::is_le),
+            ComparisonOp::Gt => ordering.is_some_and(Ordering::is_gt),
+            ComparisonOp::Ge => ordering.is_some_and(Ordering::is_ge),
+        }
+    }
 }
 
 impl StateMachineTrigger {
+    /// Creates a new [`StateMachineTrigger::Comparison`] evaluating the given [`Comparison`]
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let state_machine = AnimationStateMachine::from_states(
+    ///     "walk",
+    ///     [AnimationState::empty("walk"), AnimationState::empty("run")],
+    ///     vec![StateMachineTransition::new(
+    ///         "walk",
+    ///         "run",
+    ///         StateMachineTrigger::from_comparison(Comparison::new("speed", ComparisonOp::Gt, 3.)),
+    ///     )],
+    ///     [("speed", 4.0.into())],
+    /// );
+    /// assert_eq!(state_machine.evaluate_transition(), Some("run".into()));
+    /// ```
+    pub fn from_comparison(comparison: Comparison) -> Self {
+        Self::Comparison(comparison)
+    }
+
+    /// Kind of the trigger, which can be inspected without the condition functions
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// # use std::time::Duration;
+    /// let delay = Duration::from_secs(1);
+    /// assert_eq!(StateMachineTrigger::Never.kind(), TriggerKind::Never);
+    /// assert_eq!(StateMachineTrigger::Always.kind(), TriggerKind::Always);
+    /// assert_eq!(StateMachineTrigger::AlwaysAfter(delay).kind(), TriggerKind::AlwaysAfter(delay));
+    /// assert_eq!(StateMachineTrigger::from(|_| true).kind(), TriggerKind::Condition);
+    /// assert_eq!(StateMachineTrigger::from_context(|_| true).kind(), TriggerKind::Context);
+    /// let comparison = Comparison::new("speed", ComparisonOp::Gt, 1.);
+    /// assert_eq!(StateMachineTrigger::from_comparison(comparison).kind(), TriggerKind::Comparison);
+    /// ```
+    pub fn kind(&self) -> TriggerKind {
+        match self {
+            Self::Never => TriggerKind::Never,
+            Self::Always => TriggerKind::Always,
+            Self::AlwaysAfter(delay) => TriggerKind::AlwaysAfter(*delay),
+            Self::Condition(_) => TriggerKind::Condition,
+            Self::Context(_) => TriggerKind::Context,
+            Self::Comparison(_) => TriggerKind::Comparison,
+            Self::OnBlendComplete => TriggerKind::OnBlendComplete,
+        }
+    }
+
+    /// Compares two triggers by kind, and [`StateMachineTrigger::Comparison`] triggers by value
+    fn structural_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Comparison(a), Self::Comparison(b)) => a == b,
+            _ => self.kind() == other.kind(),
+        }
+    }
+
     /// Creates a new [`StateMachineTrigger::Condition`] from the given function
     ///
     /// Example
     /// ```
     /// # use bevy_simple_state_machine::StateMachineTrigger;
     /// // this trigger returns true if the state machine variable "run" is set to true
-    /// let trigger = StateMachineTrigger::from(|vars| vars["run"].is_bool(true));
+    /// let trigger = StateMachineTrigger::from(|vars| vars.get("run").is_some_and(|run| run.is_bool(true)));
     /// ```
     pub fn from(f: impl Fn(&StateMachineVariables) -> bool + Send + Sync + 'static) -> Self {
         Self::Condition(Arc::new(f))
     }
 
+    /// Creates a new [`StateMachineTrigger::Context`] from the given function
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::StateMachineTrigger;
+    /// // this trigger returns true if the state machine variable "speed" increased by more than 2 since the last frame
+    /// let trigger = StateMachineTrigger::from_context(|context| {
+    ///     context.delta("speed").is_some_and(|delta| delta > 2.)
+    /// });
+    /// ```
+    pub fn from_context(f: impl Fn(&TriggerContext) -> bool + Send + Sync + 'static) -> Self {
+        Self::Context(Arc::new(f))
+    }
+
+    /// Creates a trigger that fires once the given state has been entered exactly `count` times
+    ///
+    /// Entry counters accumulate until reset with [`AnimationStateMachine::reset_entry_count`]
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::prelude::*;
+    /// // branch to the finisher on the third attack
+    /// let transition = StateMachineTransition::immediate(
+    ///     AnimationStateRef::from_string("attack"),
+    ///     AnimationStateRef::from_string("finisher"),
+    ///     StateMachineTrigger::count_eq("attack", 3),
+    /// )
+    /// .with_priority(1);
+    /// ```
+    pub fn count_eq<T: ToString>(state_name: T, count: u32) -> Self {
+        let state_name = state_name.to_string();
+        Self::from_context(move |context| context.entry_count(&state_name) == count)
+    }
+
+    /// Creates a trigger that fires on every tick of [`StateMachineTick`] multiple of `n`
+    ///
+    /// The trigger fires at most once per tick, and never if the resource is missing
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::prelude::*;
+    /// // swap the banner every 3 turns
+    /// let transition = StateMachineTransition::new("red", "blue", StateMachineTrigger::on_tick_multiple(3));
+    /// ```
+    pub fn on_tick_multiple(n: u64) -> Self {
+        Self::from_context(move |context| {
+            context.tick().is_some_and(|tick| {
+                tick > 0
+                    && tick.checked_rem(n) == Some(0)
+                    && context.last_transition_tick() != Some(tick)
+            })
+        })
+    }
+
+    /// Creates a trigger that fires while the given [`StateMachineVariableType::Trigger`] is set
+    pub fn triggered<T: ToString>(name: T) -> Self {
+        let name = name.to_string();
+        Self::from(move |vars| vars.get(&name) == Some(&StateMachineVariableType::Trigger(true)))
+    }
+
+    /// Creates a trigger that fires while the given [`StateMachineVariableType::StringSet`]
+    /// variable contains `value`
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// # use bevy::utils::HashSet;
+    /// let state_machine = AnimationStateMachine::new(
+    ///     "idle",
+    ///     [
+    ///         ("idle", AnimationState::empty("idle")),
+    ///         ("fall", AnimationState::empty("fall")),
+    ///     ],
+    ///     vec![StateMachineTransition::new("idle", "fall", StateMachineTrigger::set_contains("tags", "airborne"))],
+    ///     [("tags", StateMachineVariableType::StringSet(HashSet::new()))],
+    /// );
+    /// assert_eq!(state_machine.evaluate_transition(), None);
+    /// let overrides = state_machine_variables([(
+    ///     "tags",
+    ///     StateMachineVariableType::StringSet(HashSet::from(["airborne".to_string()])),
+    /// )]);
+    /// assert_eq!(state_machine.preview_transition(&overrides), Some("fall".into()));
+    /// ```
+    pub fn set_contains<T: ToString>(name: T, value: impl ToString) -> Self {
+        let (name, value) = (name.to_string(), value.to_string());
+        Self::from(move |vars| vars.get(&name).is_some_and(|set| set.contains(&value)))
+    }
+
+    /// Creates a trigger that fires while the given [`StateMachineVariableType::String`]
+    /// variable satisfies `predicate`
+    ///
+    /// Missing and non string variables never fire
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// let trigger = StateMachineTrigger::string_matches("weapon", |weapon| weapon.eq_ignore_ascii_case("sword"));
+    /// let state_machine = AnimationStateMachine::new(
+    ///     "idle",
+    ///     [
+    ///         ("idle", AnimationState::empty("idle")),
+    ///         ("slash", AnimationState::empty("slash")),
+    ///     ],
+    ///     vec![StateMachineTransition::new("idle", "slash", trigger)],
+    ///     [("weapon", StateMachineVariableType::String("bow".to_string()))],
+    /// );
+    /// let overrides = state_machine_variables([("weapon", StateMachineVariableType::String("Sword".to_string()))]);
+    /// assert_eq!(state_machine.preview_transition(&overrides), Some("slash".into()));
+    /// assert_eq!(state_machine.preview_transition(&StateMachineVariables::default()), None);
+    /// ```
+    pub fn string_matches<T: ToString>(
+        name: T,
+        predicate: impl Fn(&str) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        let name = name.to_string();
+        Self::from(move |vars| match vars.get(&name) {
+            Some(StateMachineVariableType::String(value)) => predicate(value),
+            _ => false,
+        })
+    }
+
+    /// Creates a trigger that fires while the machine is in the given state
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// // only patrolling guards raise the alarm
+    /// let alarm = StateMachineTransition::from_any("alarm", StateMachineTrigger::in_state("patrol"));
+    /// let states = ["idle", "patrol", "alarm"].map(AnimationState::empty);
+    /// let mut state_machine = AnimationStateMachine::from_states("idle", states, vec![alarm], []);
+    /// assert_eq!(state_machine.evaluate_transition(), None);
+    /// state_machine.apply_transition(&"patrol".into());
+    /// assert_eq!(state_machine.evaluate_transition(), Some("alarm".into()));
+    /// ```
+    pub fn in_state<T: ToString>(state_name: T) -> Self {
+        let state_name = state_name.to_string();
+        Self::from_context(move |context| context.current_state() == state_name)
+    }
+
+    /// Creates a trigger that fires while the machine is not in the given state
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::*;
+    /// // casting can start from any state, except while stunned
+    /// let transition = StateMachineTransition::from_any(
+    ///     "cast",
+    ///     StateMachineTrigger::from(|vars| vars.get("cast").is_some_and(|cast| cast.is_bool(true)))
+    ///         .and(StateMachineTrigger::not_in_state("stunned")),
+    /// );
+    /// let states = [
+    ///     ("idle", AnimationState::empty("idle")),
+    ///     ("stunned", AnimationState::empty("stunned")),
+    ///     ("cast", AnimationState::empty("cast")),
+    /// ];
+    /// let variables = [("cast", StateMachineVariableType::Bool(true))];
+    /// let idle = AnimationStateMachine::new("idle", states.clone(), vec![transition.clone()], variables.clone());
+    /// let stunned = AnimationStateMachine::new("stunned", states, vec![transition], variables);
+    /// let no_overrides = StateMachineVariables::default();
+    /// assert_eq!(idle.preview_transition(&no_overrides), Some("cast".into()));
+    /// assert_eq!(stunned.preview_transition(&no_overrides), None);
+    /// ```
+    pub fn not_in_state<T: ToString>(state_name: T) -> Self {
+        let state_name = state_name.to_string();
+        Self::from_context(move |context| context.current_state() != state_name)
+    }
+
+    /// Combines two triggers into one firing when both of them fire
+    pub fn and(self, other: Self) -> Self {
+        Self::from_context(move |context| self.evaluate(context) && other.evaluate(context))
+    }
+
+    /// Combines two triggers into one firing when any of them fires
+    pub fn or(self, other: Self) -> Self {
+        Self::from_context(move |context| self.evaluate(context) || other.evaluate(context))
+    }
+
     /// Internal function to evaluate the state of a trigger
-    fn evaluate(&self, variables: &StateMachineVariables) -> bool {
+    fn evaluate(&self, context: &TriggerContext) -> bool {
         match self {
             Self::Never => false,
             Self::Always => true,
-            Self::Condition(f) => (f)(variables),
+            Self::AlwaysAfter(delay) => context.time_in_state >= delay.as_secs_f32(),
+            Self::Condition(f) => (f)(context.variables),
+            Self::Context(f) => (f)(context),
+            Self::Comparison(comparison) => comparison.evaluate(context.variables),
+            Self::OnBlendComplete => context.blend_completed,
         }
     }
 }
 
+/// Data available to [`StateMachineTrigger::Context`] conditions
+pub struct TriggerContext<'a> {
+    current_state: &'a str,
+    previous_state: Option<&'a str>,
+    variables: &'a StateMachineVariables,
+    deltas: &'a HashMap<String, f32>,
+    entry_counts: &'a HashMap<String, u32>,
+    time_in_state: f32,
+    tick: Option<u64>,
+    last_transition_tick: Option<u64>,
+    blend_completed: bool,
+}
+
+impl<'a> TriggerContext<'a> {
+    /// Tests if the blend into the current state completed this frame,
+    /// see [`StateMachineTrigger::OnBlendComplete`]
+    pub fn blend_completed(&self) -> bool {
+        self.blend_completed
+    }
+
+    /// Name of the current state of the machine
+    pub fn current_state(&self) -> &'a str {
+        self.current_state
+    }
+
+    /// Name of the state the machine was in before its last transition
+    ///
+    /// Together with [`TriggerContext::time_in_state`], this allows expressing combos
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::prelude::*;
+    /// // pressing attack again within 0.4s of a fresh attack1 starts the combo
+    /// let combo = StateMachineTrigger::from_context(|context| {
+    ///     context.previous_state() == Some("idle")
+    ///         && context.time_in_state() < 0.4
+    ///         && context.variable("attack").is_some_and(|attack| attack.is_bool(true))
+    /// });
+    /// ```
+    pub fn previous_state(&self) -> Option<&'a str> {
+        self.previous_state
+    }
+
+    /// State machine variables
+    pub fn variables(&self) -> &'a StateMachineVariables {
+        self.variables
+    }
+
+    /// Gets the value of the given variable
+    pub fn variable(&self, name: &str) -> Option<&'a StateMachineVariableType> {
+        self.variables.get(name)
+    }
+
+    /// Change of the given numeric variable since the previous frame
+    ///
+    /// Returns `None` for non numeric or missing variables
+    ///
+    /// Example
+    /// ```
+    /// # use bevy_simple_state_machine::prelude::*;
+    /// // dash on sudden accelerations
+    /// let accelerating = StateMachineTrigger::from_context(|context| context.delta("speed").is_some_and(|delta| delta > 2.));
+    /// ```
+    pub fn delta(&self, name: &str) -> Option<f32> {
+        self.deltas.get(name).copied()
+    }
+
+    /// Number of times the given state has been entered through a transition
+    ///
+    /// See [`AnimationStateMachine::entry_count`]
+    pub fn entry_count(&self, state_name: &str) -> u32 {
+        self.entry_counts
+            .get(state_name)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Time, in seconds, elapsed since the machine entered its current state
+    pub fn time_in_state(&self) -> f32 {
+        self.time_in_state
+    }
+
+    /// Current value of the [`StateMachineTick`] resource, if present
+    pub fn tick(&self) -> Option<u64> {
+        self.tick
+    }
+
+    /// Value of the [`StateMachineTick`] resource when the machine last took a transition
+    pub fn last_transition_tick(&self) -> Option<u64> {
+        self.last_transition_tick
+    }
+}
+
+/// Transition stored in the [`AnimationStateMachine::history`]
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub struct TransitionRecord {
+    /// Reference to the origin [`AnimationState`]
+    pub origin: AnimationStateRef,
+    /// Reference to the end [`AnimationState`]
+    pub end: AnimationStateRef,
+    /// Time elapsed since the app started, in seconds, when the transition was taken
+    pub timestamp: f32,
+}
+
+/// Saved state of an [`AnimationStateMachine`], see [`AnimationStateMachine::snapshot`]
+///
+/// The snapshot is reflectable, so it can be saved with the reflection based (de)serializers
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub struct StateMachineSnapshot {
+    /// Name of the current state
+    pub current_state: String,
+    /// Values of the variables
+    pub variables: StateMachineVariables,
+    /// Time spent in the current state, in seconds
+    pub time_in_state: f32,
+    /// Name of the state the machine was in before its last transition
+    pub previous_state: Option<String>,
+}
+
+/// System parameter listing the entities whose machine is in a given state
+///
+/// Example
+/// ```
+/// # use bevy::{ecs::system::RunSystemOnce, prelude::*};
+/// # use bevy_simple_state_machine::prelude::*;
+/// let mut world = World::new();
+/// let state_machine = |state| {
+///     AnimationStateMachine::from_states(
+///         state,
+///         [AnimationState::empty("idle"), AnimationState::empty("stagger")],
+///         vec![],
+///         [],
+///     )
+/// };
+/// let staggered = world.spawn(state_machine("stagger")).id();
+/// world.spawn(state_machine("idle"));
+/// let entities = world.run_system_once(|entities: EntitiesInState| {
+///     entities.in_state("stagger").collect::<Vec<_>>()
+/// });
+/// assert_eq!(entities, [staggered]);
+/// ```
+#[derive(SystemParam)]
+pub struct EntitiesInState<'w, 's> {
+    state_machines_query: Query<'w, 's, (Entity, &'static AnimationStateMachine)>,
+}
+
+impl<'w, 's> EntitiesInState<'w, 's> {
+    /// Iterates over the entities whose machine is in the named state
+    pub fn in_state<'a>(&'a self, state_name: &'a str) -> impl Iterator<Item = Entity> + 'a {
+        self.state_machines_query
+            .iter()
+            .filter(move |(_, state_machine)| state_machine.is_current_state(state_name))
+            .map(|(entity, _)| entity)
+    }
+}
+
 /// Event emitted once a [`StateMachineTransition`] has been executed
 ///
 /// ## Note
@@ -552,4 +5270,143 @@ pub struct TransitionEndedEvent {
     pub origin: AnimationStateRef,
     /// Reference to the end [`AnimationState`]
     pub end: AnimationStateRef,
+    /// Kind of the trigger that fired the transition
+    ///
+    /// Example
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_simple_state_machine::prelude::*;
+    /// fn count_automatic_transitions(mut events: EventReader<TransitionEndedEvent>, mut count: Local<usize>) {
+    ///     *count += events.read().filter(|event| event.trigger_kind == TriggerKind::Always).count();
+    /// }
+    /// ```
+    pub trigger_kind: TriggerKind,
+    /// Tag of the transition, see [`StateMachineTransition::tag`]
+    pub tag: Option<String>,
+}
+
+/// Event emitted when the playback of a state crosses one of its [`AnimationState::markers`]
+///
+/// Example
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_simple_state_machine::AnimationMarkerEvent;
+/// fn play_footsteps(mut events: EventReader<AnimationMarkerEvent>) {
+///     for event in events.read().filter(|event| event.label == "step") {
+///         info!("{:?} stepped in {}", event.entity, event.state);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Event)]
+pub struct AnimationMarkerEvent {
+    /// The entity of the state machine
+    pub entity: Entity,
+    /// Reference to the [`AnimationState`] owning the marker
+    pub state: AnimationStateRef,
+    /// Label of the crossed marker
+    pub label: String,
+}
+
+/// Event resetting every [`AnimationStateMachine`] when sent
+///
+/// See [`AnimationStateMachine::reset`]
+///
+/// Example
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_simple_state_machine::prelude::*;
+/// fn restart_level(mut resets: EventWriter<ResetAllStateMachines>) {
+///     resets.send(ResetAllStateMachines);
+/// }
+/// ```
+#[derive(Debug, Clone, Event)]
+pub struct ResetAllStateMachines;
+
+/// Event emitted on every frame of a blending [`StateMachineTransition`]
+///
+/// ## Note
+/// These events are only sent if enabled with [`SimpleStateMachinePlugin::progress_events`]
+#[derive(Debug, Clone, Event)]
+pub struct TransitionProgressEvent {
+    /// The entity on which the transition is being executed
+    pub entity: Entity,
+    /// Blend progress, in the range `[0, 1]`
+    ///
+    /// This matches [`AnimationStateMachine::transition_progress`]
+    pub progress: f32,
+}
+
+/// Event emitted once a newly added [`AnimationStateMachine`] starts playing its starting state
+///
+/// The event is sent only once per machine: replaying the starting state after
+/// [`AnimationStateMachine::reset`] does not send it again
+///
+/// ## Note
+/// This is not sent if the automatic initialization is disabled
+/// with [`SimpleStateMachinePlugin::auto_init`]
+///
+/// Example
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_simple_state_machine::prelude::*;
+/// fn log_initialized(mut events: EventReader<StateMachineInitializedEvent>) {
+///     for event in events.read() {
+///         info!("{:?} starts in {:?}", event.entity, event.starting_state);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Event)]
+pub struct StateMachineInitializedEvent {
+    /// The entity on which the state machine has been initialized
+    pub entity: Entity,
+    /// Reference to the starting [`AnimationState`]
+    pub starting_state: AnimationStateRef,
+}
+
+/// Global turn counter for turn based games
+///
+/// Insert this resource and advance it once per turn to drive
+/// [`StateMachineTrigger::on_tick_multiple`] triggers independently of [`Time`]
+///
+/// Example
+/// ```
+/// # use bevy_simple_state_machine::StateMachineTick;
+/// # use bevy::prelude::*;
+/// fn end_turn(mut tick: ResMut<StateMachineTick>) {
+///     tick.advance();
+/// }
+/// ```
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub struct StateMachineTick(pub u64);
+
+impl StateMachineTick {
+    /// Increments the counter by one
+    pub fn advance(&mut self) {
+        self.0 += 1;
+    }
+}
+
+/// Named animation clips shared by every machine, see [`AnimationState::from_library`]
+///
+/// States reference the clips by name, and the names are resolved when each machine is initialized
+#[derive(Resource, Default, Debug, Clone)]
+pub struct ClipLibrary {
+    clips: HashMap<String, Handle<AnimationClip>>,
+}
+
+impl ClipLibrary {
+    /// Adds a named clip to the library, returning the clip previously registered with the name
+    pub fn insert<T: ToString>(
+        &mut self,
+        name: T,
+        clip: Handle<AnimationClip>,
+    ) -> Option<Handle<AnimationClip>> {
+        self.clips.insert(name.to_string(), clip)
+    }
+
+    /// Gets the clip registered with the given name
+    pub fn get(&self, name: &str) -> Option<&Handle<AnimationClip>> {
+        self.clips.get(name)
+    }
 }
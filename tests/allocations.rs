@@ -0,0 +1,61 @@
+//! Tests of the heap allocations, kept apart since they install a global allocator
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use bevy_simple_state_machine::prelude::*;
+
+/// Counts the allocations of the current thread
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Counts the allocations made by `f` on the current thread
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn update_variable_str_does_not_allocate_for_existing_variables() {
+    let fast = StateMachineTrigger::from_comparison(Comparison::new("speed", ComparisonOp::Gt, 3.));
+    let mut state_machine = AnimationStateMachine::from_states(
+        "walk",
+        ["walk", "run"].map(AnimationState::empty),
+        vec![StateMachineTransition::new("walk", "run", fast)],
+        [("speed", 0.0.into())],
+    );
+    state_machine.set_lazy_evaluation(true);
+    state_machine.declare_variable_clamped("speed", 0., 10.);
+
+    let per_frame = allocations(|| {
+        for frame in 0..100 {
+            state_machine.update_variable_str("speed", (frame as f32).into());
+        }
+    });
+    assert_eq!(per_frame, 0);
+    // the counter does see the allocated names
+    assert!(
+        allocations(|| {
+            state_machine.update_variable("speed", 1.0.into());
+        }) > 0
+    );
+}
@@ -0,0 +1,84 @@
+//! App and clip setup shared by the integration tests
+#![allow(dead_code)]
+
+use std::time::Duration;
+
+use bevy::{
+    animation::{EntityPath, Interpolation, Keyframes, VariableCurve},
+    ecs::event::ManualEventReader,
+    prelude::*,
+    time::TimeUpdateStrategy,
+};
+use bevy_simple_state_machine::prelude::*;
+
+/// Time elapsed on every update of the apps built by [`app`], but the first one
+pub const FRAME: Duration = Duration::from_millis(250);
+
+/// Builds an app running the animation and state machine plugins without rendering
+pub fn app(plugin: SimpleStateMachinePlugin) -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default(), AnimationPlugin))
+        .add_plugins(plugin)
+        .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME));
+    app
+}
+
+/// Builds a clip lasting `duration` seconds
+pub fn clip(duration: f32) -> AnimationClip {
+    let mut clip = AnimationClip::default();
+    clip.add_curve_to_path(
+        EntityPath {
+            parts: vec![Name::new("root")],
+        },
+        VariableCurve {
+            keyframe_timestamps: vec![0., duration],
+            keyframes: Keyframes::Translation(vec![Vec3::ZERO, Vec3::X]),
+            interpolation: Interpolation::Linear,
+        },
+    );
+    clip
+}
+
+/// Adds a clip lasting `duration` seconds to the assets of `app`
+pub fn add_clip(app: &mut App, duration: f32) -> Handle<AnimationClip> {
+    app.world
+        .resource_mut::<Assets<AnimationClip>>()
+        .add(clip(duration))
+}
+
+/// Spawns `state_machine` on an entity with an [`AnimationPlayer`]
+pub fn spawn(app: &mut App, state_machine: AnimationStateMachine) -> Entity {
+    app.world
+        .spawn((AnimationPlayer::default(), state_machine))
+        .id()
+}
+
+/// Runs `frames` updates of `app`
+pub fn update(app: &mut App, frames: usize) {
+    for _ in 0..frames {
+        app.update();
+    }
+}
+
+/// Gets the machine of `entity`
+pub fn state_machine(app: &App, entity: Entity) -> &AnimationStateMachine {
+    app.world.get::<AnimationStateMachine>(entity).unwrap()
+}
+
+/// Gets the machine of `entity` mutably
+pub fn state_machine_mut(app: &mut App, entity: Entity) -> Mut<'_, AnimationStateMachine> {
+    app.world.get_mut::<AnimationStateMachine>(entity).unwrap()
+}
+
+/// Gets the player of `entity`
+pub fn player(app: &App, entity: Entity) -> &AnimationPlayer {
+    app.world.get::<AnimationPlayer>(entity).unwrap()
+}
+
+/// Reads the events of type `E` sent since the last call with the same `reader`
+pub fn read_events<E: Event + Clone>(app: &App, reader: &mut ManualEventReader<E>) -> Vec<E> {
+    reader
+        .read(app.world.resource::<Events<E>>())
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,39 @@
+//! Tests of the debug overlay
+#![cfg(feature = "debug")]
+mod common;
+
+use bevy::{prelude::*, ui::UiScale};
+use bevy_simple_state_machine::prelude::*;
+use common::*;
+
+#[test]
+fn debug_labels_list_the_state_variables_and_transitions() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    app.init_resource::<UiScale>();
+    let running =
+        StateMachineTrigger::from(|vars| vars.get("speed").is_some_and(|speed| speed.is_f32(1.)));
+    let mut idle = AnimationStateMachine::from_states(
+        "idle",
+        ["idle", "run", "jump", "dead"].map(AnimationState::empty),
+        vec![
+            StateMachineTransition::new("idle", "run", running),
+            StateMachineTransition::always("idle", "jump"),
+            StateMachineTransition::always("idle", "dead"),
+        ],
+        [("speed", 0.0.into())],
+    );
+    idle.set_transition_enabled("idle", "dead", false);
+    idle.set_lock_transitions(true);
+    app.world
+        .spawn((AnimationPlayer::default(), SpatialBundle::default(), idle));
+    update(&mut app, 2);
+    let mut labels = app.world.query::<&Text>();
+    let labels: Vec<_> = labels
+        .iter(&app.world)
+        .map(|text| text.sections[0].value.to_owned())
+        .collect();
+    assert_eq!(
+        labels,
+        ["idle (blocked)\nspeed = F32(0.0)\n-> run: waiting\n-> jump: fires\n-> dead: disabled"]
+    );
+}
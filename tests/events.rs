@@ -0,0 +1,141 @@
+mod common;
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_simple_state_machine::prelude::*;
+use common::*;
+
+#[test]
+fn marker_events_are_sent_once_per_loop() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let walk_clip = add_clip(&mut app, 1.);
+    let mut reader = app
+        .world
+        .resource::<Events<AnimationMarkerEvent>>()
+        .get_reader();
+    spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "walk",
+            [AnimationState::new("walk", walk_clip)
+                .looping(true)
+                .marker(0.6, "step")],
+            vec![],
+            [],
+        ),
+    );
+    // a bit over two loops of the clip
+    let mut footsteps = 0;
+    for _ in 0..12 {
+        app.update();
+        footsteps += read_events(&app, &mut reader)
+            .iter()
+            .filter(|event| event.label == "step")
+            .count();
+    }
+    assert_eq!(footsteps, 2);
+}
+
+#[test]
+fn progress_events_follow_a_blend_up_to_one() {
+    let mut app = app(SimpleStateMachinePlugin::new().progress_events(true));
+    let mut reader = app
+        .world
+        .resource::<Events<TransitionProgressEvent>>()
+        .get_reader();
+    spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "idle",
+            ["idle", "walk"].map(AnimationState::empty),
+            vec![StateMachineTransition::blend(
+                "idle".into(),
+                "walk".into(),
+                StateMachineTrigger::Always,
+                Duration::from_secs(1),
+            )],
+            [],
+        ),
+    );
+    let mut progress = Vec::new();
+    for _ in 0..8 {
+        app.update();
+        progress.extend(
+            read_events(&app, &mut reader)
+                .iter()
+                .map(|event| event.progress),
+        );
+    }
+    assert_eq!(progress, [0., 0.25, 0.5, 0.75, 1.]);
+}
+
+#[test]
+fn transition_ended_events_carry_the_transition_tag() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let mut reader = app
+        .world
+        .resource::<Events<TransitionEndedEvent>>()
+        .get_reader();
+    spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "idle",
+            ["idle", "jump"].map(AnimationState::empty),
+            vec![StateMachineTransition::always("idle", "jump").with_tag("whoosh")],
+            [],
+        ),
+    );
+    app.update();
+    let tags: Vec<_> = read_events(&app, &mut reader)
+        .into_iter()
+        .map(|event| event.tag)
+        .collect();
+    assert_eq!(tags, [Some("whoosh".to_owned())]);
+}
+
+#[test]
+fn transition_ended_events_carry_the_trigger_kind() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let mut reader = app
+        .world
+        .resource::<Events<TransitionEndedEvent>>()
+        .get_reader();
+    spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "idle",
+            ["idle", "run"].map(AnimationState::empty),
+            vec![StateMachineTransition::always("idle", "run")],
+            [],
+        ),
+    );
+    app.update();
+    let kinds: Vec<_> = read_events(&app, &mut reader)
+        .into_iter()
+        .map(|event| event.trigger_kind)
+        .collect();
+    assert_eq!(kinds, [TriggerKind::Always]);
+}
+
+#[test]
+fn initialized_events_are_sent_once_per_machine() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let mut reader = app
+        .world
+        .resource::<Events<StateMachineInitializedEvent>>()
+        .get_reader();
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states("idle", [AnimationState::empty("idle")], vec![], []),
+    );
+    let mut initialized = 0;
+    for reset in [false, true, false] {
+        if reset {
+            state_machine_mut(&mut app, entity).reset();
+        }
+        app.update();
+        initialized += read_events(&app, &mut reader).len();
+    }
+    assert_eq!(initialized, 1);
+}
@@ -0,0 +1,55 @@
+//! Tests of the logged warnings, kept apart since they install a global subscriber
+mod common;
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use bevy::{
+    log::tracing_subscriber::{
+        layer::{Context, SubscriberExt},
+        Layer, Registry,
+    },
+    utils::tracing::{self, Event, Level, Subscriber},
+};
+use bevy_simple_state_machine::prelude::*;
+use common::*;
+
+/// Counts the warnings logged
+struct WarningCounter(Arc<AtomicUsize>);
+
+impl<S: Subscriber> Layer<S> for WarningCounter {
+    fn on_event(&self, event: &Event<'_>, _context: Context<'_, S>) {
+        if *event.metadata().level() == Level::WARN {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+#[test]
+fn warn_and_first_logs_ambiguous_transitions() {
+    let warnings = Arc::new(AtomicUsize::new(0));
+    tracing::subscriber::set_global_default(
+        Registry::default().with(WarningCounter(warnings.clone())),
+    )
+    .unwrap();
+
+    let mut app =
+        app(SimpleStateMachinePlugin::new().multi_match_policy(MultiMatchPolicy::WarnAndFirst));
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "idle",
+            ["idle", "walk", "hurt"].map(AnimationState::empty),
+            vec![
+                StateMachineTransition::always("idle", "walk"),
+                StateMachineTransition::always("idle", "hurt").with_priority(10),
+            ],
+            [],
+        ),
+    );
+    app.update();
+    assert!(state_machine(&app, entity).is_current_state("walk"));
+    assert_eq!(warnings.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,550 @@
+mod common;
+
+use std::time::Duration;
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use bevy_simple_state_machine::prelude::*;
+use common::*;
+
+#[test]
+fn locked_transitions_keep_the_clip_playing() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let idle_clip = add_clip(&mut app, 2.);
+    let run =
+        StateMachineTrigger::from(|vars| vars.get("run").is_some_and(|run| run.is_bool(true)));
+    let mut locked = AnimationStateMachine::from_states(
+        "idle",
+        [
+            AnimationState::new("idle", idle_clip),
+            AnimationState::empty("run"),
+        ],
+        vec![StateMachineTransition::new("idle", "run", run)],
+        [("run", true.into())],
+    );
+    locked.set_lock_transitions(true);
+    let entity = spawn(&mut app, locked);
+    update(&mut app, 3);
+    assert!(state_machine(&app, entity).is_current_state("idle"));
+    assert!(player(&app, entity).seek_time() > 0.);
+
+    state_machine_mut(&mut app, entity).set_lock_transitions(false);
+    app.update();
+    assert!(state_machine(&app, entity).is_current_state("run"));
+}
+
+#[test]
+fn entry_transitions_choose_the_starting_state() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let mut initialized = app
+        .world
+        .resource::<Events<StateMachineInitializedEvent>>()
+        .get_reader();
+    spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "idle",
+            ["idle", "aim"].map(AnimationState::empty),
+            vec![],
+            [("armed", true.into())],
+        )
+        .with_entry_transitions(vec![StateMachineTransition::from_any(
+            "aim",
+            StateMachineTrigger::from(|vars| {
+                vars.get("armed").is_some_and(|armed| armed.is_bool(true))
+            }),
+        )]),
+    );
+    app.update();
+    let started: Vec<_> = read_events(&app, &mut initialized)
+        .iter()
+        .map(|event| event.starting_state.to_string())
+        .collect();
+    assert_eq!(started, ["aim"]);
+}
+
+#[test]
+fn stopped_machines_keep_their_state_until_reset() {
+    let mut stopped = AnimationStateMachine::from_states(
+        "idle",
+        ["idle", "run"].map(AnimationState::empty),
+        vec![StateMachineTransition::always("idle", "run")],
+        [],
+    );
+    stopped.stop();
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let entity = spawn(&mut app, stopped);
+    update(&mut app, 3);
+    assert!(state_machine(&app, entity).is_current_state("idle"));
+
+    state_machine_mut(&mut app, entity).reset();
+    app.update();
+    let state_machine = state_machine(&app, entity);
+    assert!(!state_machine.is_stopped());
+    assert!(state_machine.is_current_state("run"));
+}
+
+#[test]
+fn history_keeps_the_last_transitions() {
+    let mut app = app(SimpleStateMachinePlugin::new().transition_history(2));
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "a",
+            ["a", "b", "c"].map(AnimationState::empty),
+            vec![
+                StateMachineTransition::always("a", "b"),
+                StateMachineTransition::always("b", "c"),
+                StateMachineTransition::always("c", "a"),
+            ],
+            [],
+        ),
+    );
+    update(&mut app, 3);
+    let ends: Vec<_> = state_machine(&app, entity)
+        .history()
+        .iter()
+        .map(|record| record.end.to_string())
+        .collect();
+    assert_eq!(ends, ["c", "a"]);
+}
+
+#[test]
+fn explicit_and_descendant_players_are_driven() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let idle_clip = Handle::weak_from_u128(1);
+    let idle = AnimationStateMachine::from_states(
+        "idle",
+        [AnimationState::new("idle", idle_clip.clone())],
+        vec![],
+        [],
+    );
+    // a player found among the descendants
+    let model = app.world.spawn(AnimationPlayer::default()).id();
+    let scene = app
+        .world
+        .spawn(SpatialBundle::default())
+        .push_children(&[model])
+        .id();
+    let character = app
+        .world
+        .spawn((SpatialBundle::default(), idle.clone()))
+        .push_children(&[scene])
+        .id();
+    // a player set explicitly
+    let puppet = app.world.spawn(AnimationPlayer::default()).id();
+    app.world.spawn(idle.with_player_entity(puppet));
+
+    app.update();
+    for entity in [model, puppet] {
+        assert_eq!(player(&app, entity).animation_clip(), &idle_clip);
+    }
+    assert_eq!(state_machine(&app, character).player_entity(), Some(model));
+}
+
+#[test]
+fn additional_players_play_the_same_states() {
+    let (idle_clip, draw_clip, draw_sword_clip) = (
+        Handle::weak_from_u128(1),
+        Handle::weak_from_u128(2),
+        Handle::weak_from_u128(3),
+    );
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let cape = app.world.spawn(AnimationPlayer::default()).id();
+    let sword = app.world.spawn(AnimationPlayer::default()).id();
+    let mut draw = AnimationStateMachine::from_states(
+        "idle",
+        [
+            AnimationState::new("idle", idle_clip),
+            AnimationState::new("draw", draw_clip.clone()),
+        ],
+        vec![StateMachineTransition::always("idle", "draw")],
+        [],
+    )
+    .with_additional_player(cape)
+    .with_additional_player(sword);
+    draw.set_additional_player_clip(sword, "draw", draw_sword_clip.clone());
+    spawn(&mut app, draw);
+    app.update();
+    assert_eq!(player(&app, cape).animation_clip(), &draw_clip);
+    assert_eq!(player(&app, sword).animation_clip(), &draw_sword_clip);
+}
+
+#[test]
+fn uninterruptible_blends_delay_new_transitions() {
+    let hit =
+        StateMachineTrigger::from(|vars| vars.get("hit").is_some_and(|hit| hit.is_bool(true)));
+    let interruptible = AnimationStateMachine::from_states(
+        "idle",
+        ["idle", "walk", "hurt"].map(AnimationState::empty),
+        vec![
+            StateMachineTransition::blend(
+                "idle".into(),
+                "walk".into(),
+                StateMachineTrigger::Always,
+                Duration::from_secs(1),
+            ),
+            StateMachineTransition::blend(
+                "walk".into(),
+                "hurt".into(),
+                hit,
+                Duration::from_secs(1),
+            ),
+        ],
+        [("hit", false.into())],
+    );
+    let mut uninterruptible = interruptible.clone();
+    uninterruptible.set_interruptible_blends(false);
+
+    for (machine, expected_state) in [(interruptible, "hurt"), (uninterruptible, "walk")] {
+        let mut app = app(SimpleStateMachinePlugin::new());
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            100,
+        )));
+        let entity = spawn(&mut app, machine);
+        update(&mut app, 2);
+        let mut walking = state_machine_mut(&mut app, entity);
+        assert!(walking.is_current_state("walk"));
+        walking.update_variable("hit", true.into());
+        app.update();
+        let state_machine = state_machine(&app, entity);
+        assert!(state_machine.is_current_state(expected_state));
+        if expected_state == "hurt" {
+            assert_eq!(state_machine.transition_progress(), Some(0.));
+        }
+    }
+}
+
+#[test]
+fn time_scale_slows_down_and_freezes_blends() {
+    let mut slowed = AnimationStateMachine::from_states(
+        "idle",
+        ["idle", "walk"].map(AnimationState::empty),
+        vec![StateMachineTransition::blend(
+            "idle".into(),
+            "walk".into(),
+            StateMachineTrigger::Always,
+            Duration::from_secs(1),
+        )],
+        [],
+    );
+    slowed.set_time_scale(0.5);
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let entity = spawn(&mut app, slowed);
+    update(&mut app, 2);
+    let walking = state_machine(&app, entity);
+    assert_eq!(walking.transition_progress(), Some(0.125));
+    assert_eq!(walking.time_in_state(), 0.125);
+
+    // freeze the blend
+    state_machine_mut(&mut app, entity).set_time_scale(0.);
+    update(&mut app, 3);
+    let walking = state_machine(&app, entity);
+    assert_eq!(walking.transition_progress(), Some(0.125));
+    assert_eq!(walking.time_in_state(), 0.125);
+}
+
+#[test]
+fn transition_resolver_redirects_taken_transitions() {
+    let mut attack = AnimationStateMachine::from_states(
+        "idle",
+        ["idle", "attack", "attack_sword"].map(AnimationState::empty),
+        vec![StateMachineTransition::always("idle", "attack")],
+        [("weapon", "sword".into())],
+    );
+    attack.set_transition_resolver(|end_state, variables| {
+        match (end_state, variables.get("weapon")) {
+            (
+                AnimationStateRef::StateName(name),
+                Some(StateMachineVariableType::String(weapon)),
+            ) => AnimationStateRef::from_string(format!("{name}_{weapon}")),
+            _ => end_state.to_owned(),
+        }
+    });
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let entity = spawn(&mut app, attack);
+    app.update();
+    assert!(state_machine(&app, entity).is_current_state("attack_sword"));
+}
+
+#[test]
+fn redundant_transitions_are_suppressed_by_default() {
+    for (suppress, entries) in [(true, 1), (false, 4)] {
+        let mut hit = AnimationStateMachine::from_states(
+            "idle",
+            ["idle", "hit"].map(AnimationState::empty),
+            vec![StateMachineTransition::from_any(
+                "hit",
+                StateMachineTrigger::Always,
+            )],
+            [],
+        );
+        hit.set_suppress_redundant_transitions(suppress);
+        let mut app = app(SimpleStateMachinePlugin::new());
+        let entity = spawn(&mut app, hit);
+        update(&mut app, 4);
+        let state_machine = state_machine(&app, entity);
+        assert!(state_machine.is_current_state("hit"));
+        assert_eq!(state_machine.entry_count("hit"), entries);
+    }
+}
+
+#[test]
+fn just_changed_is_set_on_the_frame_of_a_transition() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "idle",
+            ["idle", "run"].map(AnimationState::empty),
+            vec![StateMachineTransition::always("idle", "run")],
+            [],
+        ),
+    );
+    app.update();
+    assert!(state_machine(&app, entity).just_changed());
+    app.update();
+    assert!(!state_machine(&app, entity).just_changed());
+}
+
+#[test]
+fn transition_duration_is_set_while_blending() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "idle",
+            ["idle", "run"].map(AnimationState::empty),
+            vec![StateMachineTransition::blend(
+                "idle".into(),
+                "run".into(),
+                StateMachineTrigger::Always,
+                Duration::from_millis(300),
+            )],
+            [],
+        ),
+    );
+    assert_eq!(state_machine(&app, entity).transition_duration(), None);
+    app.update();
+    assert_eq!(state_machine(&app, entity).transition_duration(), Some(0.3));
+}
+
+#[test]
+fn next_transition_follows_the_plugin_configuration() {
+    let go = || StateMachineTrigger::from(|vars| vars["go"].is_bool(true));
+    let machine = AnimationStateMachine::from_states(
+        "idle",
+        ["idle", "walk", "hurt"].map(AnimationState::empty),
+        vec![
+            StateMachineTransition::new("idle", "walk", go()),
+            StateMachineTransition::new("idle", "hurt", go()).with_priority(10),
+        ],
+        [("go", false.into())],
+    );
+    // a machine that was never updated uses the default policy
+    let mut preview = machine.clone();
+    preview.update_variable("go", true.into());
+    assert_eq!(preview.next_transition().unwrap().end_state, "hurt".into());
+
+    let mut app =
+        app(SimpleStateMachinePlugin::new().multi_match_policy(MultiMatchPolicy::FirstMatch));
+    let entity = spawn(&mut app, machine);
+    app.update();
+
+    let mut idle = state_machine_mut(&mut app, entity);
+    idle.update_variable("go", true.into());
+    assert_eq!(idle.next_transition().unwrap().end_state, "walk".into());
+    idle.set_lock_transitions(true);
+    assert!(idle.next_transition().is_none());
+    idle.set_lock_transitions(false);
+    app.update();
+    assert!(state_machine(&app, entity).is_current_state("walk"));
+}
+
+#[test]
+fn reset_all_state_machines_restarts_every_machine() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    for _ in 0..2 {
+        let mut stopped =
+            AnimationStateMachine::from_states("idle", [AnimationState::empty("idle")], vec![], []);
+        stopped.stop();
+        spawn(&mut app, stopped);
+    }
+    app.world.send_event(ResetAllStateMachines);
+    app.update();
+    let mut state_machines = app.world.query::<&AnimationStateMachine>();
+    assert!(state_machines
+        .iter(&app.world)
+        .all(|state_machine| !state_machine.is_stopped()));
+}
+
+fn lazy_walk() -> AnimationStateMachine {
+    let fast = StateMachineTrigger::from_comparison(Comparison::new("speed", ComparisonOp::Gt, 3.));
+    let mut walk = AnimationStateMachine::from_states(
+        "walk",
+        ["walk", "run", "crouch"].map(AnimationState::empty),
+        vec![StateMachineTransition::new("walk", "run", fast)],
+        [("speed", 1.0.into()), ("grounded", true.into())],
+    );
+    walk.set_lazy_evaluation(true);
+    walk
+}
+
+#[test]
+fn lazy_evaluation_reevaluates_transitions_when_their_inputs_change() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let entity = spawn(&mut app, lazy_walk());
+    app.update();
+    // "grounded" is not an input of the transition, which is skipped
+    state_machine_mut(&mut app, entity).update_variable("grounded", false.into());
+    app.update();
+    assert!(state_machine(&app, entity).is_current_state("walk"));
+    // "speed" is, so the transition is evaluated again
+    state_machine_mut(&mut app, entity).update_variable_str("speed", 4.0.into());
+    app.update();
+    assert!(state_machine(&app, entity).is_current_state("run"));
+}
+
+#[test]
+fn lazy_evaluation_reevaluates_transitions_when_they_change() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let entity = spawn(&mut app, lazy_walk());
+    update(&mut app, 2);
+    state_machine_mut(&mut app, entity).add_transition(StateMachineTransition::new(
+        "walk",
+        "crouch",
+        StateMachineTrigger::from_comparison(Comparison::new("speed", ComparisonOp::Lt, 2.)),
+    ));
+    app.update();
+    assert!(state_machine(&app, entity).is_current_state("crouch"));
+}
+
+#[test]
+fn lazy_evaluation_only_skips_transitions_in_the_plugin() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let entity = spawn(&mut app, lazy_walk());
+    update(&mut app, 2);
+    let mut walk = state_machine_mut(&mut app, entity);
+    walk.set_transition_enabled("walk", "run", false);
+    walk.set_transition_enabled("walk", "run", true);
+    *walk.variable_mut("speed").unwrap() = 5.0.into();
+    assert_eq!(walk.eligible_transitions().len(), 1);
+    assert_eq!(walk.evaluate_transition(), Some("run".into()));
+    app.update();
+    assert!(state_machine(&app, entity).is_current_state("run"));
+}
+
+#[test]
+fn sub_machines_run_while_the_parent_is_in_their_state() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let mut reader = app
+        .world
+        .resource::<Events<TransitionEndedEvent>>()
+        .get_reader();
+    let combat = AnimationStateMachine::from_states(
+        "guard",
+        ["guard", "strike"].map(AnimationState::empty),
+        vec![StateMachineTransition::always("guard", "strike")],
+        [],
+    );
+    let in_combat = StateMachineTrigger::from(|vars| {
+        vars.get("combat")
+            .is_some_and(|combat| combat.is_bool(true))
+    });
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "idle",
+            [
+                AnimationState::empty("idle"),
+                AnimationState::empty("combat").sub_machine(combat),
+            ],
+            vec![StateMachineTransition::new("idle", "combat", in_combat)],
+            [("combat", false.into())],
+        ),
+    );
+    let strikes = |app: &App, reader: &mut _| {
+        read_events::<TransitionEndedEvent>(app, reader)
+            .iter()
+            .filter(|event| event.end == "strike".into())
+            .count()
+    };
+    update(&mut app, 3);
+    assert_eq!(strikes(&app, &mut reader), 0);
+    state_machine_mut(&mut app, entity).update_variable("combat", true.into());
+    update(&mut app, 3);
+    assert_eq!(strikes(&app, &mut reader), 1);
+}
+
+#[test]
+fn sub_machine_triggers_see_the_parent_variables() {
+    let locomotion = AnimationStateMachine::from_states(
+        "walk",
+        ["walk", "sprint"].map(AnimationState::empty),
+        vec![StateMachineTransition::new(
+            "walk",
+            "sprint",
+            StateMachineTrigger::from_context(|context| {
+                context.delta("speed").is_some_and(|delta| delta > 1.)
+            }),
+        )],
+        [],
+    );
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "move",
+            [AnimationState::empty("move").sub_machine(locomotion)],
+            vec![],
+            [("speed", 1.0.into())],
+        ),
+    );
+    let is_sprinting = |app: &App| {
+        let host = state_machine(app, entity).state("move").unwrap();
+        host.sub_machine
+            .as_ref()
+            .unwrap()
+            .is_current_state("sprint")
+    };
+    update(&mut app, 2);
+    assert!(!is_sprinting(&app));
+    state_machine_mut(&mut app, entity).update_variable("speed", 3.0.into());
+    app.update();
+    assert!(is_sprinting(&app));
+}
+
+#[test]
+fn sub_machines_run_in_non_interruptible_and_terminal_states() {
+    let child = || {
+        AnimationStateMachine::from_states(
+            "guard",
+            ["guard", "strike"].map(AnimationState::empty),
+            vec![StateMachineTransition::always("guard", "strike")],
+            [],
+        )
+    };
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let hosts = [
+        AnimationState::empty("combat")
+            .sub_machine(child())
+            .interruptible(false),
+        AnimationState::empty("combat")
+            .sub_machine(child())
+            .terminal(true),
+    ]
+    .map(|host| {
+        spawn(
+            &mut app,
+            AnimationStateMachine::from_states("combat", [host], vec![], []),
+        )
+    });
+    update(&mut app, 2);
+    for entity in hosts {
+        let host = state_machine(&app, entity).state("combat").unwrap();
+        assert!(host
+            .sub_machine
+            .as_ref()
+            .unwrap()
+            .is_current_state("strike"));
+    }
+}
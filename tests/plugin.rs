@@ -0,0 +1,170 @@
+mod common;
+
+use std::time::Duration;
+
+use bevy::{ecs::system::RunSystemOnce, prelude::*, time::TimeUpdateStrategy};
+use bevy_simple_state_machine::prelude::*;
+use common::*;
+
+#[test]
+fn finish_epsilon_leaves_non_interruptible_states_earlier() {
+    // the attack is left one frame earlier with a larger tolerance
+    for (epsilon, frames) in [
+        (SimpleStateMachinePlugin::DEFAULT_FINISH_EPSILON, 6),
+        (0.3, 5),
+    ] {
+        let mut app = app(SimpleStateMachinePlugin::new().finish_epsilon(epsilon));
+        let attack_clip = add_clip(&mut app, 1.);
+        let entity = spawn(
+            &mut app,
+            AnimationStateMachine::from_states(
+                "attack",
+                [
+                    AnimationState::new("attack", attack_clip).interruptible(false),
+                    AnimationState::empty("idle"),
+                ],
+                vec![StateMachineTransition::always("attack", "idle")],
+                [],
+            ),
+        );
+        update(&mut app, frames - 1);
+        assert!(state_machine(&app, entity).is_current_state("attack"));
+        app.update();
+        assert!(state_machine(&app, entity).is_current_state("idle"));
+    }
+}
+
+#[test]
+fn starting_state_is_played_before_its_transitions() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "intro",
+            ["intro", "idle"].map(AnimationState::empty),
+            vec![StateMachineTransition::always("intro", "idle")],
+            [],
+        ),
+    );
+    let mut initialized = app
+        .world
+        .resource::<Events<StateMachineInitializedEvent>>()
+        .get_reader();
+    let mut transitions = app
+        .world
+        .resource::<Events<TransitionEndedEvent>>()
+        .get_reader();
+    app.update();
+    let started: Vec<_> = read_events(&app, &mut initialized)
+        .iter()
+        .map(|event| event.starting_state.to_string())
+        .collect();
+    assert_eq!(started, ["intro"]);
+    let origins: Vec<_> = read_events(&app, &mut transitions)
+        .iter()
+        .map(|event| event.origin.to_string())
+        .collect();
+    assert_eq!(origins, ["intro"]);
+}
+
+#[test]
+fn machines_without_auto_init_wait_for_play_current() {
+    let mut app = app(SimpleStateMachinePlugin::new().auto_init(false));
+    let intro_clip = Handle::weak_from_u128(1);
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "intro",
+            [
+                AnimationState::new("intro", intro_clip.clone()),
+                AnimationState::empty("idle"),
+            ],
+            vec![StateMachineTransition::always("intro", "idle")],
+            [],
+        ),
+    );
+    update(&mut app, 3);
+    assert!(state_machine(&app, entity).is_current_state("intro"));
+    assert_eq!(player(&app, entity).animation_clip(), &Handle::default());
+
+    app.world.run_system_once(
+        |mut query: Query<(&mut AnimationStateMachine, &mut AnimationPlayer)>,
+         animations: Res<Assets<AnimationClip>>| {
+            for (mut state_machine, mut player) in &mut query {
+                state_machine.play_current(&mut player, &animations);
+            }
+        },
+    );
+    assert_eq!(player(&app, entity).animation_clip(), &intro_clip);
+    app.update();
+    assert!(state_machine(&app, entity).is_current_state("idle"));
+}
+
+#[test]
+fn multi_match_policy_chooses_among_matching_transitions() {
+    for (policy, expected_state) in [
+        (MultiMatchPolicy::FirstMatch, "walk"),
+        (MultiMatchPolicy::HighestPriority, "hurt"),
+    ] {
+        let mut app = app(SimpleStateMachinePlugin::new().multi_match_policy(policy));
+        let entity = spawn(
+            &mut app,
+            AnimationStateMachine::from_states(
+                "idle",
+                ["idle", "walk", "hurt"].map(AnimationState::empty),
+                vec![
+                    StateMachineTransition::always("idle", "walk"),
+                    StateMachineTransition::always("idle", "hurt").with_priority(10),
+                ],
+                [],
+            ),
+        );
+        app.update();
+        assert!(state_machine(&app, entity).is_current_state(expected_state));
+    }
+}
+
+#[test]
+fn max_delta_limits_the_time_of_a_frame() {
+    let mut app = app(SimpleStateMachinePlugin::new().max_delta(0.1));
+    // every frame is a 5 seconds stall
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs(5)));
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "idle",
+            ["idle", "fidget"].map(AnimationState::empty),
+            vec![StateMachineTransition::new(
+                "idle",
+                "fidget",
+                StateMachineTrigger::AlwaysAfter(Duration::from_secs(1)),
+            )],
+            [],
+        ),
+    );
+    update(&mut app, 5);
+    let state_machine = state_machine(&app, entity);
+    assert_eq!(state_machine.entry_count("fidget"), 0);
+    assert!(state_machine.time_in_state() <= 0.5);
+}
+
+#[test]
+fn stop_on_remove_pauses_the_player() {
+    let mut app = app(SimpleStateMachinePlugin::new().stop_on_remove(true));
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "walk",
+            [AnimationState::new("walk", Handle::weak_from_u128(1))],
+            vec![],
+            [],
+        ),
+    );
+    app.update();
+    assert!(!player(&app, entity).is_paused());
+    app.world
+        .entity_mut(entity)
+        .remove::<AnimationStateMachine>();
+    app.update();
+    assert!(player(&app, entity).is_paused());
+}
@@ -0,0 +1,298 @@
+mod common;
+
+use std::time::Duration;
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use bevy_simple_state_machine::prelude::*;
+use common::*;
+
+#[test]
+fn finish_behavior_sets_the_player_state_at_the_end_of_the_clip() {
+    for on_finish_behavior in [
+        FinishBehavior::Hold,
+        FinishBehavior::Loop,
+        FinishBehavior::Stop,
+    ] {
+        let mut app = app(SimpleStateMachinePlugin::new());
+        let wave_clip = add_clip(&mut app, 0.5);
+        let wave = AnimationState::new("wave", wave_clip).on_finish_behavior(on_finish_behavior);
+        let entity = spawn(
+            &mut app,
+            AnimationStateMachine::from_states("wave", [wave], vec![], []),
+        );
+        update(&mut app, 6);
+        let player = player(&app, entity);
+        match on_finish_behavior {
+            FinishBehavior::Hold => assert!(player.is_paused() && player.seek_time() == 0.5),
+            FinishBehavior::Loop => assert!(!player.is_paused() && player.completions() > 0),
+            FinishBehavior::Stop => assert!(player.is_paused() && player.seek_time() == 0.),
+        }
+    }
+}
+
+#[test]
+fn sync_group_keeps_the_normalized_time() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::ZERO));
+    let walk_clip = add_clip(&mut app, 1.);
+    let run_clip = add_clip(&mut app, 2.);
+    let run =
+        StateMachineTrigger::from(|vars| vars.get("run").is_some_and(|run| run.is_bool(true)));
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "walk",
+            [
+                AnimationState::new("walk", walk_clip).sync_group("locomotion"),
+                AnimationState::new("run", run_clip).sync_group("locomotion"),
+            ],
+            vec![StateMachineTransition::new("walk", "run", run)],
+            [("run", false.into())],
+        ),
+    );
+    app.update();
+    app.world
+        .get_mut::<AnimationPlayer>(entity)
+        .unwrap()
+        .seek_to(0.25);
+    state_machine_mut(&mut app, entity).update_variable("run", true.into());
+    app.update();
+    // a quarter of the run clip
+    assert_eq!(player(&app, entity).seek_time(), 0.5);
+}
+
+#[test]
+fn outro_clip_plays_before_the_next_state() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let holster_clip = add_clip(&mut app, 1.);
+    let walk_clip = Handle::weak_from_u128(1);
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "aim",
+            [
+                AnimationState::empty("aim").outro_clip(holster_clip.clone()),
+                AnimationState::new("walk", walk_clip.clone()),
+            ],
+            vec![StateMachineTransition::always("aim", "walk")],
+            [],
+        ),
+    );
+    update(&mut app, 2);
+    assert!(state_machine(&app, entity).is_current_state("walk"));
+    assert_eq!(player(&app, entity).animation_clip(), &holster_clip);
+    update(&mut app, 4);
+    assert_eq!(player(&app, entity).animation_clip(), &walk_clip);
+}
+
+#[test]
+fn unloaded_outro_clips_are_waited_for() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let holster_clip = Handle::weak_from_u128(2);
+    let walk_clip = add_clip(&mut app, 1.);
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "aim",
+            [
+                AnimationState::empty("aim").outro_clip(holster_clip.clone()),
+                AnimationState::new("walk", walk_clip.clone()),
+            ],
+            vec![StateMachineTransition::always("aim", "walk")],
+            [],
+        ),
+    );
+    update(&mut app, 6);
+    assert_eq!(player(&app, entity).animation_clip(), &holster_clip);
+
+    app.world
+        .resource_mut::<Assets<AnimationClip>>()
+        .insert(&holster_clip, clip(0.5));
+    update(&mut app, 4);
+    assert_eq!(player(&app, entity).animation_clip(), &walk_clip);
+}
+
+#[test]
+fn additional_players_wait_for_the_outro_clip() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let (aim_clip, holster_clip, walk_clip) = (
+        add_clip(&mut app, 1.),
+        add_clip(&mut app, 1.),
+        add_clip(&mut app, 1.),
+    );
+    let cape = app.world.spawn(AnimationPlayer::default()).id();
+    spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "aim",
+            [
+                AnimationState::new("aim", aim_clip.clone()).outro_clip(holster_clip),
+                AnimationState::new("walk", walk_clip.clone()),
+            ],
+            vec![StateMachineTransition::always("aim", "walk")],
+            [],
+        )
+        .with_additional_player(cape),
+    );
+    update(&mut app, 2);
+    assert_eq!(player(&app, cape).animation_clip(), &aim_clip);
+    update(&mut app, 5);
+    assert_eq!(player(&app, cape).animation_clip(), &walk_clip);
+}
+
+#[test]
+fn non_interruptible_states_wait_for_their_clip_to_load() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let attack_clip = Handle::weak_from_u128(1);
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "attack",
+            [
+                AnimationState::new("attack", attack_clip.clone()).interruptible(false),
+                AnimationState::empty("idle"),
+            ],
+            vec![StateMachineTransition::always("attack", "idle")],
+            [],
+        ),
+    );
+    update(&mut app, 4);
+    // the clip is still loading
+    assert!(state_machine(&app, entity).is_current_state("attack"));
+
+    app.world
+        .resource_mut::<Assets<AnimationClip>>()
+        .insert(attack_clip, clip(0.5));
+    update(&mut app, 4);
+    assert!(state_machine(&app, entity).is_current_state("idle"));
+}
+
+#[test]
+fn clip_paths_are_loaded_on_initialization() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "walk",
+            [AnimationState::from_path(
+                "walk",
+                "character.glb#Animation0",
+            )],
+            vec![],
+            [],
+        ),
+    );
+    app.update();
+    let walk_clip = state_machine(&app, entity)
+        .state("walk")
+        .unwrap()
+        .clip
+        .to_owned();
+    assert_ne!(walk_clip, Handle::default());
+    assert_eq!(player(&app, entity).animation_clip(), &walk_clip);
+}
+
+#[test]
+fn library_clips_are_resolved_on_initialization() {
+    let walk_clip = Handle::weak_from_u128(1);
+    let mut library = ClipLibrary::default();
+    library.insert("walk", walk_clip.clone());
+    let mut app = app(SimpleStateMachinePlugin::new());
+    app.insert_resource(library);
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "walk",
+            [AnimationState::from_library("walk", "walk")],
+            vec![],
+            [],
+        ),
+    );
+    app.update();
+    assert_eq!(player(&app, entity).animation_clip(), &walk_clip);
+}
+
+#[test]
+fn empty_states_pause_the_player() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let stop = || StateMachineTrigger::from(|vars| vars["stop"].is_bool(true));
+    let walk = || StateMachineTrigger::from(|vars| vars["stop"].is_bool(false));
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "walk",
+            [
+                AnimationState::new("walk", Handle::weak_from_u128(1)),
+                AnimationState::empty("stopped"),
+            ],
+            vec![
+                StateMachineTransition::new("walk", "stopped", stop()),
+                StateMachineTransition::new("stopped", "walk", walk()),
+            ],
+            [("stop", false.into())],
+        ),
+    );
+    for (stop, expected_state) in [(false, "walk"), (true, "stopped"), (false, "walk")] {
+        state_machine_mut(&mut app, entity).update_variable("stop", stop.into());
+        app.update();
+        assert!(state_machine(&app, entity).is_current_state(expected_state));
+        assert_eq!(player(&app, entity).is_paused(), stop);
+    }
+}
+
+#[test]
+fn reverse_playback_starts_from_the_end_of_the_clip() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let rewind_clip = add_clip(&mut app, 1.);
+    let rewind = AnimationState::new("rewind", rewind_clip).playback(PlaybackDirection::Reverse);
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states("rewind", [rewind], vec![], []),
+    );
+    app.update();
+    assert!((player(&app, entity).seek_time() - 1.).abs() < 1e-3);
+    app.update();
+    assert!((player(&app, entity).seek_time() - 0.75).abs() < 1e-3);
+}
+
+#[test]
+fn frozen_states_pause_the_player_on_the_current_pose() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let walk_clip = add_clip(&mut app, 4.);
+    let stunned = StateMachineTrigger::from(|vars| {
+        vars.get("stunned")
+            .is_some_and(|stunned| stunned.is_bool(true))
+    });
+    let recovered = StateMachineTrigger::from(|vars| {
+        vars.get("stunned")
+            .is_some_and(|stunned| stunned.is_bool(false))
+    });
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "walk",
+            [
+                AnimationState::new("walk", walk_clip.clone()),
+                AnimationState::new("stunned", walk_clip).frozen(true),
+            ],
+            vec![
+                StateMachineTransition::new("walk", "stunned", stunned),
+                StateMachineTransition::new("stunned", "walk", recovered),
+            ],
+            [("stunned", false.into())],
+        ),
+    );
+    update(&mut app, 3);
+    state_machine_mut(&mut app, entity).update_variable("stunned", true.into());
+    update(&mut app, 3);
+    assert!(player(&app, entity).is_paused());
+    let frozen_time = player(&app, entity).seek_time();
+    assert!(frozen_time > 0.);
+
+    state_machine_mut(&mut app, entity).update_variable("stunned", false.into());
+    app.update();
+    let player = player(&app, entity);
+    assert!(!player.is_paused());
+    // the clip is resumed rather than restarted
+    assert!(player.seek_time() > frozen_time);
+}
@@ -0,0 +1,344 @@
+mod common;
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use bevy_simple_state_machine::prelude::*;
+use common::*;
+
+#[test]
+fn transition_clip_plays_before_the_end_state() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let turn_clip = add_clip(&mut app, 1.);
+    let run_clip = Handle::weak_from_u128(1);
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "idle",
+            [
+                AnimationState::empty("idle"),
+                AnimationState::new("run", run_clip.clone()),
+            ],
+            vec![StateMachineTransition::always("idle", "run")
+                .with_transition_clip(turn_clip.clone())],
+            [],
+        ),
+    );
+    update(&mut app, 2);
+    assert!(state_machine(&app, entity).is_current_state("run"));
+    assert_eq!(player(&app, entity).animation_clip(), &turn_clip);
+    update(&mut app, 4);
+    assert_eq!(player(&app, entity).animation_clip(), &run_clip);
+}
+
+#[test]
+fn unloaded_transition_clips_are_waited_for() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let turn_clip = Handle::weak_from_u128(2);
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "idle",
+            ["idle", "run", "jump"].map(AnimationState::empty),
+            vec![
+                StateMachineTransition::always("idle", "run")
+                    .with_transition_clip(turn_clip.clone()),
+                StateMachineTransition::always("run", "jump"),
+            ],
+            [],
+        ),
+    );
+    update(&mut app, 6);
+    assert!(state_machine(&app, entity).is_current_state("run"));
+    assert_eq!(player(&app, entity).animation_clip(), &turn_clip);
+
+    app.world
+        .resource_mut::<Assets<AnimationClip>>()
+        .insert(&turn_clip, clip(0.5));
+    update(&mut app, 4);
+    assert!(state_machine(&app, entity).is_current_state("jump"));
+}
+
+#[test]
+fn triggers_see_the_variables_from_before_side_effects() {
+    let seen_grounded = Arc::new(AtomicBool::new(false));
+    let seen = seen_grounded.clone();
+    let fall = StateMachineTrigger::from(move |vars| {
+        seen.store(vars["grounded"].is_bool(true), Ordering::SeqCst);
+        false
+    });
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "idle",
+            ["idle", "jump", "fall"].map(AnimationState::empty),
+            vec![
+                StateMachineTransition::always("idle", "jump")
+                    .with_priority(1)
+                    .with_on_take(|vars| {
+                        vars.insert("grounded".to_string(), false.into());
+                    }),
+                StateMachineTransition::new("idle", "fall", fall),
+            ],
+            [("grounded", true.into())],
+        ),
+    );
+    app.update();
+    let state_machine = state_machine(&app, entity);
+    assert!(state_machine.is_current_state("jump"));
+    assert!(state_machine
+        .variable("grounded")
+        .is_some_and(|grounded| grounded.is_bool(false)));
+    // the trigger evaluated on the same frame still saw the old value
+    assert!(seen_grounded.load(Ordering::SeqCst));
+}
+
+#[test]
+fn closed_gates_block_transitions_until_they_open() {
+    // "attack" can't be reached while the character is stunned
+    let transition = StateMachineTransition::always("idle", "attack")
+        .with_enabled_while("stunned", StateMachineVariableType::Bool(false));
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "idle",
+            ["idle", "attack"].map(AnimationState::empty),
+            vec![transition],
+            [("stunned", true.into())],
+        ),
+    );
+    update(&mut app, 2);
+    assert!(state_machine(&app, entity).is_current_state("idle"));
+    state_machine_mut(&mut app, entity).update_variable("stunned", false.into());
+    app.update();
+    assert!(state_machine(&app, entity).is_current_state("attack"));
+}
+
+#[test]
+fn side_effects_run_once_when_the_transition_is_taken() {
+    let transition = StateMachineTransition::new(
+        "idle",
+        "jump",
+        StateMachineTrigger::from(|vars| {
+            vars.get("jump_requested")
+                .is_some_and(|jump| jump.is_bool(true))
+        }),
+    )
+    .with_on_take(|vars| {
+        vars.insert("jump_requested".to_string(), false.into());
+        let Some(StateMachineVariableType::I32(jumps)) = vars.get_mut("jumps") else {
+            return;
+        };
+        *jumps += 1;
+    });
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "idle",
+            ["idle", "jump"].map(AnimationState::empty),
+            vec![transition],
+            [("jump_requested", false.into()), ("jumps", 0.into())],
+        ),
+    );
+    let jumps = |app: &App| state_machine(app, entity).variable("jumps").cloned();
+    update(&mut app, 2);
+    assert_eq!(jumps(&app), Some(StateMachineVariableType::I32(0)));
+    state_machine_mut(&mut app, entity).update_variable("jump_requested", true.into());
+    update(&mut app, 3);
+    let state_machine = state_machine(&app, entity);
+    assert!(state_machine.is_current_state("jump"));
+    assert!(state_machine
+        .variable("jump_requested")
+        .is_some_and(|jump| jump.is_bool(false)));
+    assert_eq!(jumps(&app), Some(StateMachineVariableType::I32(1)));
+}
+
+#[test]
+fn always_after_waits_for_the_time_in_state() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "intro",
+            ["intro", "idle"].map(AnimationState::empty),
+            vec![StateMachineTransition::new(
+                "intro",
+                "idle",
+                StateMachineTrigger::AlwaysAfter(Duration::from_millis(500)),
+            )],
+            [],
+        ),
+    );
+    // the first frame has no elapsed time
+    for expected_state in ["intro", "intro", "idle"] {
+        app.update();
+        assert!(state_machine(&app, entity).is_current_state(expected_state));
+    }
+}
+
+#[test]
+fn on_blend_complete_fires_after_the_blend() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "idle",
+            ["idle", "walk", "run"].map(AnimationState::empty),
+            vec![
+                StateMachineTransition::blend(
+                    "idle".into(),
+                    "walk".into(),
+                    StateMachineTrigger::Always,
+                    Duration::from_millis(500),
+                ),
+                StateMachineTransition::new("walk", "run", StateMachineTrigger::OnBlendComplete),
+                StateMachineTransition::new("run", "idle", StateMachineTrigger::OnBlendComplete),
+            ],
+            [],
+        ),
+    );
+    for expected_state in ["walk", "walk", "walk", "run", "run", "run"] {
+        app.update();
+        assert_eq!(
+            state_machine(&app, entity).current_state_name(),
+            expected_state
+        );
+    }
+}
+
+#[test]
+fn count_eq_fires_on_the_given_entry() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "recover",
+            ["attack", "recover", "finisher"].map(AnimationState::empty),
+            vec![
+                StateMachineTransition::immediate(
+                    AnimationStateRef::from_string("attack"),
+                    AnimationStateRef::from_string("finisher"),
+                    StateMachineTrigger::count_eq("attack", 3),
+                )
+                .with_priority(1),
+                StateMachineTransition::always("attack", "recover"),
+                StateMachineTransition::always("recover", "attack"),
+            ],
+            [],
+        ),
+    );
+    for expected_state in [
+        "attack", "recover", "attack", "recover", "attack", "finisher",
+    ] {
+        app.update();
+        assert!(state_machine(&app, entity).is_current_state(expected_state));
+    }
+}
+
+#[test]
+fn on_tick_multiple_fires_once_per_matching_tick() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    app.init_resource::<StateMachineTick>();
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "red",
+            ["red", "blue"].map(AnimationState::empty),
+            vec![
+                StateMachineTransition::new(
+                    "red",
+                    "blue",
+                    StateMachineTrigger::on_tick_multiple(3),
+                ),
+                StateMachineTransition::new(
+                    "blue",
+                    "red",
+                    StateMachineTrigger::on_tick_multiple(3),
+                ),
+            ],
+            [],
+        ),
+    );
+    for expected_state in ["red", "red", "blue", "blue", "blue", "red"] {
+        app.world.resource_mut::<StateMachineTick>().advance();
+        // a single transition is taken per tick, however many frames it lasts
+        for _ in 0..3 {
+            app.update();
+            assert!(state_machine(&app, entity).is_current_state(expected_state));
+        }
+    }
+}
+
+#[test]
+fn previous_state_allows_combos() {
+    let attack_pressed = |vars: &StateMachineVariables| {
+        vars.get("attack")
+            .is_some_and(|attack| attack.is_bool(true))
+    };
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "idle",
+            ["idle", "attack1", "combo"].map(AnimationState::empty),
+            vec![
+                StateMachineTransition::new(
+                    "idle",
+                    "attack1",
+                    StateMachineTrigger::from(attack_pressed),
+                )
+                .with_on_take(|vars| {
+                    vars.insert("attack".to_string(), false.into());
+                }),
+                StateMachineTransition::new(
+                    "attack1",
+                    "combo",
+                    StateMachineTrigger::from_context(move |context| {
+                        context.previous_state() == Some("idle")
+                            && context.time_in_state() < 0.4
+                            && attack_pressed(context.variables())
+                    }),
+                ),
+            ],
+            [("attack", true.into())],
+        ),
+    );
+    app.update();
+    assert_eq!(state_machine(&app, entity).previous_state(), Some("idle"));
+    state_machine_mut(&mut app, entity).update_variable("attack", true.into());
+    app.update();
+    let state_machine = state_machine(&app, entity);
+    assert_eq!(state_machine.entry_count("combo"), 1);
+    assert_eq!(state_machine.previous_state(), Some("attack1"));
+}
+
+#[test]
+fn delta_is_the_change_since_the_previous_frame() {
+    let mut app = app(SimpleStateMachinePlugin::new());
+    let accelerating = StateMachineTrigger::from_context(|context| {
+        context.delta("speed").is_some_and(|delta| delta > 2.)
+    });
+    let entity = spawn(
+        &mut app,
+        AnimationStateMachine::from_states(
+            "run",
+            ["run", "dash"].map(AnimationState::empty),
+            vec![StateMachineTransition::new("run", "dash", accelerating)],
+            [("speed", 1.0.into())],
+        ),
+    );
+    for (speed, expected_state) in [(1., "run"), (3., "run"), (4., "run"), (7., "dash")] {
+        state_machine_mut(&mut app, entity).update_variable("speed", speed.into());
+        app.update();
+        assert!(state_machine(&app, entity).is_current_state(expected_state));
+    }
+}